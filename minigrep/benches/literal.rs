@@ -0,0 +1,69 @@
+// Compares the literal fast path in search() and in streaming search_reader(),
+// which is what minigrep itself uses for stdin and most files, against the per-line filters
+// they replaced.
+// Run with: cargo bench --bench literal
+use std::io::{BufRead, BufReader};
+use std::time::{Duration, Instant};
+
+use minigrep::matcher::Matcher;
+use minigrep::stream::BUFFER_SIZE;
+
+// the old search(): split into lines first, then look for the query in every line
+fn search_per_line<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    contents.lines()
+        .filter(|line| line.contains(query))
+        .collect()
+}
+
+// the old search_reader(): read every line, then look for the query in it
+fn stream_per_line(query: &str, contents: &str) -> usize {
+    BufReader::with_capacity(BUFFER_SIZE, contents.as_bytes())
+        .lines()
+        .filter(|line| line.as_ref().unwrap().contains(query))
+        .count()
+}
+
+// best time over a few runs, to keep noise out of the comparison
+fn time<F: FnMut() -> usize>(mut f: F) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut found = 0;
+    for _ in 0..10 {
+        let start = Instant::now();
+        found = f();
+        best = best.min(start.elapsed());
+    }
+    (best, found)
+}
+
+fn main() {
+    // ~50MB of log-like lines with a rare needle
+    let mut contents = String::new();
+    for i in 0..1_000_000 {
+        if i % 10_000 == 0 {
+            contents.push_str("2024-01-01 12:00:00 ERROR connection reset by peer, retrying\n");
+        } else {
+            contents.push_str("2024-01-01 12:00:00 INFO request handled in 12ms status=200 path=/\n");
+        }
+    }
+    let query = "connection reset";
+    let mb = contents.len() as f64 / (1024.0 * 1024.0);
+
+    let (per_line, found_per_line) = time(|| search_per_line(query, &contents).len());
+    let (literal, found_literal) = time(|| minigrep::search(query, &contents).len());
+    assert_eq!(found_per_line, found_literal);
+
+    let matcher = Matcher::substring(query, true);
+    let (stream_lines, found_stream_lines) = time(|| stream_per_line(query, &contents));
+    let (stream_literal, found_stream_literal) = time(|| {
+        let reader = BufReader::with_capacity(BUFFER_SIZE, contents.as_bytes());
+        minigrep::search_reader(&matcher, reader).count()
+    });
+    assert_eq!(found_per_line, found_stream_lines);
+    assert_eq!(found_per_line, found_stream_literal);
+
+    println!("searching {:.1}MB for {:?} ({} matching lines)", mb, query, found_literal);
+    println!("per-line filter: {:>10.2?} ({:.0} MB/s)", per_line, mb / per_line.as_secs_f64());
+    println!("literal search:  {:>10.2?} ({:.0} MB/s)", literal, mb / literal.as_secs_f64());
+    println!("streaming, per-line: {:>10.2?} ({:.0} MB/s)", stream_lines, mb / stream_lines.as_secs_f64());
+    println!("streaming, literal:  {:>10.2?} ({:.0} MB/s)", stream_literal, mb / stream_literal.as_secs_f64());
+}
@@ -1,29 +1,619 @@
 use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::error::Error;
+use std::fmt;
+use std::thread;
+
+use crate::encoding::Encoding;
+
+pub const USAGE: &str = "\
+Usage: minigrep [OPTIONS] PATTERN [FILE...]
+       minigrep [OPTIONS] -e PATTERN... [FILE...]
+       minigrep [OPTIONS] -f PATTERN_FILE... [FILE...]
+
+With no FILE, or when FILE is -, read standard input.
+
+Options:
+  -E, --extended-regexp      treat PATTERN as a regular expression
+  -e, --regexp=PATTERN       search for PATTERN; can be given more than once,
+                             a line is selected if any PATTERN matches
+  -f, --file=FILE            read patterns from FILE, one per line
+  -i, --ignore-case          case insensitive search; with -E only chars with a single char
+                             case folding are matched, so 'ß' matches 'ẞ' but not 'SS'
+  -s, --case-sensitive       case sensitive search (overrides CASE_INSENSITIVE)
+  -S, --smart-case           case insensitive if PATTERN is all lowercase, sensitive otherwise
+                             (the last of -i, -s and -S wins)
+  -v, --invert-match         select non-matching lines
+  -w, --word-regexp          only match whole words
+  -x, --line-regexp          only match whole lines
+  -n, --line-number          prefix each line with its line number
+  -b, --byte-offset          prefix each line with its byte offset in the file
+      --column               prefix each line with the column of the first match (implies -n)
+      --color[=WHEN]         highlight matches, file names and line numbers;
+                             WHEN is auto (the default: only on a terminal), always or never
+  -o, --only-matching        print only the matched parts of lines, each on its own line
+  -A, --after-context=NUM    print NUM lines of context after each match
+  -B, --before-context=NUM   print NUM lines of context before each match
+  -C, --context=NUM          print NUM lines of context before and after each match
+  -c, --count                print only a count of matching lines
+  -l, --files-with-matches   print only the names of files that match
+  -L, --files-without-match  print only the names of files that don't match
+  -q, --quiet                print nothing, only set the exit status
+      --replace=TEXT         print lines with every match replaced by TEXT; with -E,
+                             $1, ${name} and $$ in TEXT refer to the match's capture groups
+      --in-place             with --replace, replace the matches in the files themselves
+      --dry-run              with --in-place, print the changes as a diff instead of making them
+      --json                 print results as JSON Lines: a \"begin\" and \"end\" record for every file,
+                             a \"match\" (or \"context\") record for every line and a \"summary\" at the end
+  -r, --recursive            search directories recursively, skipping hidden files, binary files
+                             and files ignored by .gitignore or .ignore
+      --hidden               also search hidden files and directories
+      --no-ignore            don't skip files ignored by .gitignore or .ignore
+  -g, --glob=GLOB            only search files matching GLOB, or skip them if GLOB starts with !;
+                             can be given more than once
+  -a, --text                 search binary files as if they were text
+      --binary-files=TYPE    what to do with files containing NUL bytes: binary (the default:
+                             print \"Binary file FILE matches\" instead of matching lines),
+                             text (same as -a) or without-match (treat them as not matching)
+  -z, --search-zip           search inside .gz, .bz2, .xz and .zst files
+      --encoding=ENC         read input as ENC: auto (the default: UTF-16 if there is a byte
+                             order mark, else UTF-8), utf-8, utf-16le, utf-16be or latin-1;
+                             invalid UTF-8 is printed as U+FFFD
+  -j, --threads=NUM          search NUM files at a time (default: number of CPUs)
+      --no-FLAG              turn off a flag given before, like a default from the config file:
+                             --no-extended-regexp, --no-invert-match, --no-word-regexp,
+                             --no-line-regexp, --no-line-number, --no-byte-offset, --no-column,
+                             --no-only-matching, --no-recursive, --no-hidden, --no-search-zip;
+                             --ignore undoes --no-ignore and --no-glob drops the globs so far
+      --no-config            ignore the config file and MINIGREP_OPTS
+  -h, --help                 print this help and exit
+      --version              print version information and exit
+  --                         end of options, the next argument is PATTERN
+
+Environment:
+  CASE_INSENSITIVE           if set, search case insensitively unless -i, -s or -S is given
+  MINIGREP_CONFIG            the config file to use instead of ~/.config/minigrep/config
+                             (or $XDG_CONFIG_HOME/minigrep/config)
+  MINIGREP_OPTS              default options, separated by spaces
+
+Config file:
+  Default options, one per line, like --smart-case or --glob=!*.min.js; an option's value
+  can be on the next line. Empty lines and lines starting with # are skipped.
+  The defaults from the config file come first, then MINIGREP_OPTS, then the command line;
+  an option replaces an earlier one of the same kind, so -C1 replaces a default -A5 and -c
+  a default -l or --json, and a default flag is turned off with its --no- form (see --no-FLAG).
+  Patterns (-e, -f) and --in-place can't be defaults.
+";
 
 pub struct Config {
-    pub query: String,
-    pub filename: String,
+    // a line is selected if any of these match
+    pub patterns: Vec<String>,
+    pub filenames: Vec<String>,
     pub case_sensitive: bool,
+    // --smart-case was the case option in effect; case_sensitive has already been worked out from the patterns
+    pub smart_case: bool,
+    pub regex: bool,
+    pub invert_match: bool,
+    pub word_regexp: bool,
+    pub line_regexp: bool,
+    pub line_number: bool,
+    pub byte_offset: bool,
+    pub column: bool,
+    pub only_matching: bool,
+    pub after_context: usize,
+    pub before_context: usize,
+    pub output: OutputMode,
+    pub color: ColorChoice,
+    pub json: bool,
+    pub replace: Option<String>,
+    pub in_place: bool,
+    pub dry_run: bool,
+    pub recursive: bool,
+    pub hidden: bool,
+    pub no_ignore: bool,
+    pub globs: Vec<String>,
+    pub binary_files: BinaryFiles,
+    pub encoding: Encoding,
+    pub search_zip: bool,
+    // number of files searched in parallel
+    pub threads: usize,
+}
+
+// What gets printed for each file.
+// When several are given the strongest one wins, in the order they are declared here
+// (-q beats -l and -L, which beat -c), like grep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OutputMode {
+    // the selected lines (the default)
+    Lines,
+    // -c: the number of selected lines
+    Count,
+    // -l: the file name if any line was selected
+    FilesWithMatches,
+    // -L: the file name if no line was selected
+    FilesWithoutMatch,
+    // -q: nothing
+    Quiet,
 }
 
+// --color=WHEN
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    // color only when stdout is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+// --binary-files=TYPE: how files that look binary (contain a NUL byte) are searched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryFiles {
+    // search them, but only say whether they match
+    Binary,
+    // -a: search them like any other file
+    Text,
+    // don't search them at all
+    WithoutMatch,
+}
+
+// -h and --version are reported as "errors" so that main can print the text and exit
+// without needing a PATTERN and FILE
+#[derive(Debug, PartialEq)]
+pub enum ArgsError {
+    Help,
+    Version,
+    Usage(String),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgsError::Help => write!(f, "{}", USAGE),
+            ArgsError::Version => write!(f, "minigrep {}", env!("CARGO_PKG_VERSION")),
+            ArgsError::Usage(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for ArgsError {}
+
 impl Config {
     //pub fn parse_args(args: &[String]) -> Result<Config, &'static str> {
-    pub fn parse_args(mut args: env::Args) -> Result<Config, &'static str> {
+    //pub fn parse_args(mut args: env::Args) -> Result<Config, &'static str> {
+    pub fn parse_args<I>(args: I) -> Result<Config, ArgsError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        Config::parse_args_env(args, |key| env::var(key).ok())
+    }
+
+    // same as parse_args but environment variables are looked up through "getenv",
+    // so tests don't have to modify the real process environment
+    pub fn parse_args_env<I, F>(args: I, getenv: F) -> Result<Config, ArgsError>
+    where
+        I: IntoIterator<Item = String>,
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut config = Config {
+            patterns: Vec::new(),
+            filenames: Vec::new(),
+            case_sensitive: true,
+            smart_case: false,
+            regex: false,
+            invert_match: false,
+            word_regexp: false,
+            line_regexp: false,
+            line_number: false,
+            byte_offset: false,
+            column: false,
+            only_matching: false,
+            after_context: 0,
+            before_context: 0,
+            output: OutputMode::Lines,
+            color: ColorChoice::Auto,
+            json: false,
+            replace: None,
+            in_place: false,
+            dry_run: false,
+            recursive: false,
+            hidden: false,
+            no_ignore: false,
+            globs: Vec::new(),
+            binary_files: BinaryFiles::Binary,
+            encoding: Encoding::Auto,
+            search_zip: false,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+        // None until -i, -s or -S is given; explicit flags take precedence over CASE_INSENSITIVE
+        let mut case: Option<Case> = None;
+        // with -e or -f, all the positional arguments are files
+        let mut have_patterns = false;
+
+        let mut args = args.into_iter();
         // skip executable name
         args.next();
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Missing required arguments: [search_expr] [filename]")
-        };
-        let filename = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Missing required arguments: search_expr [filename]")
+        let (command_line, positional) = split_args(args)?;
+        // --no-config has to be an option itself, not the value of one like in "-e --no-config"
+        let no_config = command_line.iter().any(|(name, _)| name == "no-config");
+        let mut passes = if no_config { Vec::new() } else { default_options(&getenv)? };
+        passes.push(command_line);
+
+        // The config file, MINIGREP_OPTS and then the command line are applied one after the other.
+        // An option replaces whatever an earlier pass set of the same kind, so "-C1" on the
+        // command line replaces a default "-A5", and "-c" a default "-l" or "--json".
+        for options in passes {
+            // -A and -B take precedence over -C, whatever order they are given in
+            let mut after_context: Option<usize> = None;
+            let mut before_context: Option<usize> = None;
+            let mut context: Option<usize> = None;
+            if options.iter().any(|(name, _)| is_output_option(name)) {
+                config.output = OutputMode::Lines;
+                config.json = false;
+            }
+
+            for (name, value) in options {
+                let value = value.unwrap_or_default();
+                match name.as_str() {
+                    "extended-regexp" => config.regex = true,
+                    "regexp" => {
+                        config.patterns.push(value);
+                        have_patterns = true;
+                    }
+                    "file" => {
+                        config.patterns.extend(read_patterns(&value)?);
+                        have_patterns = true;
+                    }
+                    "ignore-case" => case = Some(Case::Insensitive),
+                    "case-sensitive" => case = Some(Case::Sensitive),
+                    "smart-case" => case = Some(Case::Smart),
+                    "invert-match" => config.invert_match = true,
+                    "word-regexp" => config.word_regexp = true,
+                    "line-regexp" => config.line_regexp = true,
+                    "line-number" => config.line_number = true,
+                    "byte-offset" => config.byte_offset = true,
+                    "column" => {
+                        config.column = true;
+                        config.line_number = true;
+                    }
+                    "only-matching" => config.only_matching = true,
+                    "after-context" => after_context = Some(parse_number(&name, &value)?),
+                    "before-context" => before_context = Some(parse_number(&name, &value)?),
+                    "context" => context = Some(parse_number(&name, &value)?),
+                    "color" => {
+                        config.color = match value.as_str() {
+                            "auto" => ColorChoice::Auto,
+                            "always" => ColorChoice::Always,
+                            "never" => ColorChoice::Never,
+                            _ => return Err(ArgsError::Usage(format!("invalid argument '{}' for '--color'", value))),
+                        }
+                    }
+                    "count" => config.output = config.output.max(OutputMode::Count),
+                    "files-with-matches" => config.output = config.output.max(OutputMode::FilesWithMatches),
+                    "files-without-match" => config.output = config.output.max(OutputMode::FilesWithoutMatch),
+                    "quiet" => config.output = OutputMode::Quiet,
+                    "json" => config.json = true,
+                    "replace" => config.replace = Some(value),
+                    "in-place" => config.in_place = true,
+                    "dry-run" => config.dry_run = true,
+                    "recursive" => config.recursive = true,
+                    "hidden" => config.hidden = true,
+                    "no-ignore" => config.no_ignore = true,
+                    "glob" => config.globs.push(value),
+                    "text" => config.binary_files = BinaryFiles::Text,
+                    "binary-files" => {
+                        config.binary_files = match value.as_str() {
+                            "binary" => BinaryFiles::Binary,
+                            "text" => BinaryFiles::Text,
+                            "without-match" => BinaryFiles::WithoutMatch,
+                            _ => return Err(ArgsError::Usage(format!("invalid argument '{}' for '--binary-files'", value))),
+                        }
+                    }
+                    "search-zip" => config.search_zip = true,
+                    "encoding" => {
+                        config.encoding = match Encoding::from_name(&value) {
+                            Some(encoding) => encoding,
+                            None => return Err(ArgsError::Usage(format!("invalid argument '{}' for '--encoding'", value))),
+                        }
+                    }
+                    "threads" => {
+                        config.threads = parse_number(&name, &value)?;
+                        if config.threads == 0 {
+                            return Err(ArgsError::Usage(String::from("option '--threads' needs at least 1 thread")));
+                        }
+                    }
+                    // turning off flags given before, so the command line can undo a default
+                    "no-extended-regexp" => config.regex = false,
+                    "no-invert-match" => config.invert_match = false,
+                    "no-word-regexp" => config.word_regexp = false,
+                    "no-line-regexp" => config.line_regexp = false,
+                    "no-line-number" => config.line_number = false,
+                    "no-byte-offset" => config.byte_offset = false,
+                    "no-column" => config.column = false,
+                    "no-only-matching" => config.only_matching = false,
+                    "no-recursive" => config.recursive = false,
+                    "no-hidden" => config.hidden = false,
+                    "ignore" => config.no_ignore = false,
+                    "no-glob" => config.globs.clear(),
+                    "no-search-zip" => config.search_zip = false,
+                    "no-config" => {}
+                    "help" => return Err(ArgsError::Help),
+                    "version" => return Err(ArgsError::Version),
+                    _ => return Err(ArgsError::Usage(format!("unknown option '--{}'", name))),
+                }
+            }
+            if let Some(after) = after_context.or(context) {
+                config.after_context = after;
+            }
+            if let Some(before) = before_context.or(context) {
+                config.before_context = before;
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        if !have_patterns {
+            match positional.next() {
+                Some(arg) => config.patterns.push(arg),
+                None => return Err(ArgsError::Usage(String::from("Missing required argument: PATTERN"))),
+            }
+        }
+        if config.json && config.output != OutputMode::Lines {
+            return Err(ArgsError::Usage(String::from("--json can't be used with -c, -l, -L or -q")));
+        }
+        if config.in_place && config.replace.is_none() {
+            return Err(ArgsError::Usage(String::from("--in-place needs --replace")));
+        }
+        if config.in_place && config.invert_match {
+            return Err(ArgsError::Usage(String::from("--in-place can't be used with -v")));
+        }
+        // files edited in place only print a diff (--dry-run), so other kinds of output can't be had
+        if config.in_place && (config.output != OutputMode::Lines || config.only_matching || config.json) {
+            return Err(ArgsError::Usage(String::from("--in-place can't be used with -c, -l, -L, -q, -o or --json")));
+        }
+        // files are edited as UTF-8 text, they can't be written back compressed or in another encoding
+        if config.in_place && (config.search_zip || !matches!(config.encoding, Encoding::Auto | Encoding::Utf8)) {
+            return Err(ArgsError::Usage(String::from("--in-place can't be used with -z or --encoding")));
+        }
+        if config.dry_run && !config.in_place {
+            return Err(ArgsError::Usage(String::from("--dry-run needs --in-place")));
+        }
+        config.filenames = positional.collect();
+        if config.filenames.is_empty() {
+            // no FILE: read standard input
+            config.filenames.push(String::from("-"));
+        }
+
+        config.smart_case = case == Some(Case::Smart);
+        config.case_sensitive = match case {
+            Some(Case::Sensitive) => true,
+            Some(Case::Insensitive) => false,
+            Some(Case::Smart) => config.patterns.iter().any(|p| has_uppercase(p, config.regex)),
+            None => getenv("CASE_INSENSITIVE").is_none(),
         };
-        let case_sensitive = env::var("CASE_INSENSITIVE").is_err();
-        Ok(Config {
-            query,
-            filename,
-            case_sensitive
-        })
+        Ok(config)
+    }
+}
+
+// Splits arguments (without the executable name) into options and positional arguments.
+// Every option is turned into its long name and an optional value,
+// so short and long forms are handled by the same match in parse_args_env.
+fn split_args<I>(args: I) -> Result<(Options, Vec<String>), ArgsError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = Vec::new();
+    let mut positional = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positional.extend(args.by_ref());
+            break;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            // --name or --name=value or --name value
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            if !takes_value(name) && !is_flag(name) {
+                return Err(ArgsError::Usage(format!("unknown option '--{}'", name)));
+            }
+            let value = match (takes_value(name), value) {
+                // --color on its own means --color=auto, it never takes the next argument
+                (true, None) if name == "color" => Some(String::from("auto")),
+                (true, None) => Some(next_value(&mut args, &arg)?),
+                (false, Some(_)) => {
+                    return Err(ArgsError::Usage(format!("option '--{}' doesn't allow an argument", name)))
+                }
+                (_, value) => value,
+            };
+            options.push((name.to_string(), value));
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            // a group of short flags, e.g. -inv or -nA3
+            for (i, flag) in arg.char_indices().skip(1) {
+                let name = match long_name(flag) {
+                    Some(name) => name,
+                    None => return Err(ArgsError::Usage(format!("unknown option '-{}'", flag))),
+                };
+                if takes_value(name) {
+                    // the value is the rest of the group, or else the next argument
+                    let rest = &arg[i + flag.len_utf8()..];
+                    let value = if rest.is_empty() {
+                        next_value(&mut args, &format!("-{}", flag))?
+                    } else {
+                        rest.to_string()
+                    };
+                    options.push((name.to_string(), Some(value)));
+                    break;
+                }
+                options.push((name.to_string(), None));
+            }
+        } else {
+            // "-" on its own is a positional argument
+            positional.push(arg);
+        }
     }
+    Ok((options, positional))
+}
+
+// The options from the config file and from MINIGREP_OPTS, one list for each that is set.
+// A config file that doesn't exist is only an error if MINIGREP_CONFIG names it.
+fn default_options<F>(getenv: &F) -> Result<Vec<Options>, ArgsError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut defaults = Vec::new();
+
+    let explicit = getenv("MINIGREP_CONFIG").map(PathBuf::from);
+    let path = explicit.clone().or_else(|| {
+        let config_dir = getenv("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| getenv("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_dir.join("minigrep").join("config"))
+    });
+    if let Some(path) = path {
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                let lines: Vec<String> = contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(String::from)
+                    .collect();
+                defaults.push(parse_defaults(lines, &path.display().to_string())?);
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound && explicit.is_none() => {}
+            Err(err) => return Err(ArgsError::Usage(format!("{}: {}", path.display(), err))),
+        }
+    }
+
+    if let Some(opts) = getenv("MINIGREP_OPTS") {
+        let opts: Vec<String> = opts.split_whitespace().map(String::from).collect();
+        defaults.push(parse_defaults(opts, "MINIGREP_OPTS")?);
+    }
+    Ok(defaults)
+}
+
+// Defaults can only be options (with their values), so they can't take the place of the
+// PATTERN or FILE arguments on the command line. Patterns (-e, -f) and --in-place can't be
+// defaults either: they would change what every search means.
+fn parse_defaults(defaults: Vec<String>, source: &str) -> Result<Options, ArgsError> {
+    let (options, positional) = split_args(defaults).map_err(|err| match err {
+        ArgsError::Usage(msg) => ArgsError::Usage(format!("{}: {}", source, msg)),
+        err => err,
+    })?;
+    // anything after "--" is positional too
+    if let Some(arg) = positional.first() {
+        return Err(ArgsError::Usage(format!("{}: '{}' is not an option", source, arg)));
+    }
+    match options.iter().find(|(name, _)| matches!(name.as_str(), "regexp" | "file" | "in-place")) {
+        Some((name, _)) => Err(ArgsError::Usage(format!("{}: option '--{}' can't be a default", source, name))),
+        None => Ok(options),
+    }
+}
+
+// options in the order they were given: long name and value
+type Options = Vec<(String, Option<String>)>;
+
+// the options that choose what is printed (see OutputMode), which replace each other
+// between the defaults and the command line
+fn is_output_option(name: &str) -> bool {
+    matches!(name, "count" | "files-with-matches" | "files-without-match" | "quiet" | "json")
+}
+
+// -i, -s and -S
+#[derive(Clone, Copy, PartialEq)]
+enum Case {
+    Sensitive,
+    Insensitive,
+    Smart,
+}
+
+// for --smart-case: does the pattern contain an uppercase letter?
+// in a regex, letters after a backslash are escapes like \W or \S, not literal text
+fn has_uppercase(pattern: &str, regex: bool) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if regex && c == '\\' {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+// long option name for a short flag
+fn long_name(flag: char) -> Option<&'static str> {
+    let name = match flag {
+        'E' => "extended-regexp",
+        'e' => "regexp",
+        'f' => "file",
+        'i' => "ignore-case",
+        's' => "case-sensitive",
+        'S' => "smart-case",
+        'v' => "invert-match",
+        'w' => "word-regexp",
+        'x' => "line-regexp",
+        'n' => "line-number",
+        'b' => "byte-offset",
+        'A' => "after-context",
+        'B' => "before-context",
+        'C' => "context",
+        'c' => "count",
+        'l' => "files-with-matches",
+        'L' => "files-without-match",
+        'q' => "quiet",
+        'r' => "recursive",
+        'j' => "threads",
+        'g' => "glob",
+        'a' => "text",
+        'o' => "only-matching",
+        'z' => "search-zip",
+        'h' => "help",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// options that need a value, e.g. -A NUM or --after-context=NUM
+fn takes_value(name: &str) -> bool {
+    matches!(name, "regexp" | "file" | "after-context" | "before-context" | "context" | "color" | "threads" | "glob" | "binary-files" | "encoding" | "replace")
+}
+
+// the long options that don't take a value
+fn is_flag(name: &str) -> bool {
+    matches!(
+        name,
+        "extended-regexp" | "ignore-case" | "case-sensitive" | "smart-case" | "invert-match" | "word-regexp"
+            | "line-regexp" | "line-number" | "byte-offset" | "column" | "only-matching" | "count"
+            | "files-with-matches" | "files-without-match" | "quiet" | "json" | "in-place" | "dry-run"
+            | "recursive" | "hidden" | "no-ignore" | "text" | "search-zip" | "no-extended-regexp"
+            | "no-invert-match" | "no-word-regexp" | "no-line-regexp" | "no-line-number" | "no-byte-offset"
+            | "no-column" | "no-only-matching" | "no-recursive" | "no-hidden" | "ignore" | "no-glob"
+            | "no-search-zip" | "no-config" | "help" | "version"
+    )
+}
+
+// -f FILE: one pattern per line
+fn read_patterns(filename: &str) -> Result<Vec<String>, ArgsError> {
+    let contents = fs::read_to_string(filename)
+        .map_err(|err| ArgsError::Usage(format!("{}: {}", filename, err)))?;
+    Ok(contents.lines().map(String::from).collect())
+}
+
+fn next_value<I>(args: &mut I, option: &str) -> Result<String, ArgsError>
+where
+    I: Iterator<Item = String>,
+{
+    args.next()
+        .ok_or_else(|| ArgsError::Usage(format!("option '{}' requires an argument", option)))
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize, ArgsError> {
+    value
+        .parse()
+        .map_err(|_| ArgsError::Usage(format!("invalid number '{}' for option '--{}'", value, name)))
 }
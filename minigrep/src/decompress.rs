@@ -0,0 +1,60 @@
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+
+// -z: compressed formats that are decompressed before searching
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+// the bytes every file of a format starts with
+const MAGIC: [(&[u8], Format); 3] = [
+    (b"\x1F\x8B", Format::Gzip),
+    (b"\xFD7zXZ\x00", Format::Xz),
+    (b"\x28\xB5\x2F\xFD", Format::Zstd),
+];
+
+// The format of a file starting with "start", going by its magic bytes,
+// or else by the extension of "path".
+pub fn detect(path: &Path, start: &[u8]) -> Option<Format> {
+    if let Some(&(_, format)) = MAGIC.iter().find(|(magic, _)| start.starts_with(magic)) {
+        return Some(format);
+    }
+    if is_bzip2(start) {
+        return Some(Format::Bzip2);
+    }
+    match path.extension()?.to_str()? {
+        "gz" | "tgz" => Some(Format::Gzip),
+        "bz2" | "tbz2" => Some(Format::Bzip2),
+        "xz" | "txz" => Some(Format::Xz),
+        "zst" => Some(Format::Zstd),
+        _ => None,
+    }
+}
+
+// Decompresses "reader" as it is read, so the whole file is never in memory.
+// Files made of several compressed streams one after the other (like concatenated
+// rotated logs) are read to the end, not just to the end of the first stream.
+pub fn decompress<'r, R: BufRead + 'r>(reader: R, format: Format) -> io::Result<Box<dyn Read + 'r>> {
+    Ok(match format {
+        Format::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+        Format::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(reader)),
+        Format::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)),
+        Format::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+    })
+}
+
+// "BZh" alone is too likely at the start of a text file: it has to be followed by the block
+// size ('1' to '9') and the magic number of the first block, or of the end of the stream
+// for an empty file
+fn is_bzip2(start: &[u8]) -> bool {
+    match start {
+        [b'B', b'Z', b'h', size, rest @ ..] if (b'1'..=b'9').contains(size) => {
+            rest.starts_with(b"\x31\x41\x59\x26\x53\x59") || rest.starts_with(b"\x17\x72\x45\x38\x50\x90")
+        }
+        _ => false,
+    }
+}
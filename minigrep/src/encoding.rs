@@ -0,0 +1,158 @@
+use std::io::{self, BufRead, Read};
+
+// --encoding=ENC: how the bytes of an input are turned into text before searching.
+// Whatever the encoding, lines are searched and printed as UTF-8,
+// and byte offsets (-b) count bytes of the UTF-8 text.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    // UTF-16 if the input starts with a UTF-16 byte order mark, else UTF-8 (the default)
+    #[default]
+    Auto,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    // ISO-8859-1: every byte is the char with the same number
+    Latin1,
+}
+
+impl Encoding {
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Some(Encoding::Auto),
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Some(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Encoding::Utf16Be),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+// "bytes" as text, if it is valid UTF-8 that Decoder would pass through as it is
+// (skipping a UTF-8 byte order mark)
+pub fn as_utf8(bytes: &[u8], encoding: Encoding) -> Option<&str> {
+    let bytes = match encoding {
+        Encoding::Auto if bytes.starts_with(UTF16LE_BOM) || bytes.starts_with(UTF16BE_BOM) => return None,
+        Encoding::Auto | Encoding::Utf8 => bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes),
+        Encoding::Utf16Le | Encoding::Utf16Be | Encoding::Latin1 => return None,
+    };
+    std::str::from_utf8(bytes).ok()
+}
+
+// Reads "reader" in "encoding" and gives back UTF-8.
+// Byte order marks are skipped. UTF-8 is passed through without copying;
+// invalid UTF-8 is left for the line reader to replace (see stream.rs).
+// Other encodings are decoded a buffer at a time, so memory use stays the same.
+pub struct Decoder<R> {
+    reader: R,
+    encoding: Encoding,
+    // decoded text that hasn't been consumed yet, from pos on
+    decoded: Vec<u8>,
+    pos: usize,
+    // UTF-16 bytes that couldn't be decoded yet: an odd byte or half of a surrogate pair
+    carry: Vec<u8>,
+}
+
+impl<R: BufRead> Decoder<R> {
+    pub fn new(mut reader: R, encoding: Encoding) -> io::Result<Decoder<R>> {
+        let start = reader.fill_buf()?;
+        let (encoding, bom) = match encoding {
+            Encoding::Auto if start.starts_with(UTF16LE_BOM) => (Encoding::Utf16Le, UTF16LE_BOM.len()),
+            Encoding::Auto if start.starts_with(UTF16BE_BOM) => (Encoding::Utf16Be, UTF16BE_BOM.len()),
+            Encoding::Auto | Encoding::Utf8 if start.starts_with(UTF8_BOM) => (Encoding::Utf8, UTF8_BOM.len()),
+            Encoding::Auto => (Encoding::Utf8, 0),
+            Encoding::Utf16Le if start.starts_with(UTF16LE_BOM) => (encoding, UTF16LE_BOM.len()),
+            Encoding::Utf16Be if start.starts_with(UTF16BE_BOM) => (encoding, UTF16BE_BOM.len()),
+            _ => (encoding, 0),
+        };
+        reader.consume(bom);
+        Ok(Decoder { reader, encoding, decoded: Vec::new(), pos: 0, carry: Vec::new() })
+    }
+
+    // decodes the next buffer of the reader into "decoded", which is empty on return only at the end
+    fn decode_more(&mut self) -> io::Result<()> {
+        self.decoded.clear();
+        self.pos = 0;
+        while self.decoded.is_empty() {
+            let buf = self.reader.fill_buf()?;
+            let len = buf.len();
+            if len == 0 && self.carry.is_empty() {
+                return Ok(());
+            }
+            match self.encoding {
+                Encoding::Latin1 => {
+                    let text: String = buf.iter().map(|&b| char::from(b)).collect();
+                    self.decoded.extend_from_slice(text.as_bytes());
+                }
+                Encoding::Utf16Le | Encoding::Utf16Be => {
+                    self.carry.extend_from_slice(buf);
+                    self.decode_utf16(len == 0);
+                }
+                Encoding::Auto | Encoding::Utf8 => unreachable!("UTF-8 isn't decoded"),
+            }
+            self.reader.consume(len);
+        }
+        Ok(())
+    }
+
+    // decodes as much of carry as possible; at the end of the input, all of it
+    fn decode_utf16(&mut self, end_of_input: bool) {
+        let big_endian = self.encoding == Encoding::Utf16Be;
+        let unit = |pair: &[u8]| {
+            let pair = [pair[0], pair[1]];
+            if big_endian { u16::from_be_bytes(pair) } else { u16::from_le_bytes(pair) }
+        };
+
+        let mut end = self.carry.len() & !1;
+        // a high surrogate at the end needs the next unit, which may be in the next buffer
+        if !end_of_input && end >= 2 && (0xD800..0xDC00).contains(&unit(&self.carry[end - 2..end])) {
+            end -= 2;
+        }
+        let units = self.carry[..end].chunks_exact(2).map(unit);
+        let mut text = String::with_capacity(end);
+        for c in char::decode_utf16(units) {
+            text.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+        if end_of_input && end < self.carry.len() {
+            // odd number of bytes
+            text.push(char::REPLACEMENT_CHARACTER);
+            end = self.carry.len();
+        }
+        self.decoded.extend_from_slice(text.as_bytes());
+        self.carry.drain(..end);
+    }
+}
+
+impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let buf = self.fill_buf()?;
+        let len = buf.len().min(out.len());
+        out[..len].copy_from_slice(&buf[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.encoding == Encoding::Utf8 {
+            return self.reader.fill_buf();
+        }
+        if self.pos == self.decoded.len() {
+            self.decode_more()?;
+        }
+        Ok(&self.decoded[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.encoding == Encoding::Utf8 {
+            self.reader.consume(amt);
+        } else {
+            self.pos += amt;
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::iter;
+use std::ops::Range;
+
+use caseless::Caseless;
+
+// Unicode case folding for case insensitive search.
+// Lowercasing isn't enough to compare text without case: "ß" has no single char uppercase
+// and should match "SS", and Greek final sigma "ς" should match "Σ" like "σ" does.
+// Folding maps both sides to the same form before comparing them.
+// This is the full default folding from CaseFolding.txt (statuses C and F, from the caseless crate):
+// "I" folds to "i" and "İ" to "i̇", not the Turkic ones.
+
+// appends the case folded form of c to out
+pub fn push_folded(c: char, out: &mut String) {
+    out.extend(iter::once(c).default_case_fold());
+}
+
+pub fn fold(s: &str) -> String {
+    let mut folded = String::with_capacity(s.len());
+    for c in s.chars() {
+        push_folded(c, &mut folded);
+    }
+    folded
+}
+
+// A line folded with fold(), remembering where each byte of the folded text came from,
+// so that matches found in the folded text can be mapped back to the original line.
+// A folded char can be longer than the original ("ß" -> "ss").
+pub struct Folded<'a> {
+    line: &'a str,
+    pub text: String,
+    // for every byte of text, the offset in line of the char it came from
+    starts: Vec<usize>,
+}
+
+impl<'a> Folded<'a> {
+    pub fn new(line: &'a str) -> Folded<'a> {
+        let mut text = String::with_capacity(line.len());
+        let mut starts = Vec::with_capacity(line.len());
+        for (i, c) in line.char_indices() {
+            push_folded(c, &mut text);
+            starts.resize(text.len(), i);
+        }
+        Folded { line, text, starts }
+    }
+
+    // the offset in text of the folding of the char at "i" in line, or of the first char after it
+    pub fn folded_offset(&self, i: usize) -> usize {
+        self.starts.partition_point(|&start| start < i)
+    }
+
+    // the range in the original line covering the chars that "range" of text came from
+    pub fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        let start = self.starts.get(range.start).copied().unwrap_or(self.line.len());
+        if range.is_empty() {
+            return start..start;
+        }
+        // end of the original char that the last matched byte came from
+        let last = self.starts[range.end - 1];
+        let end = last + self.line[last..].chars().next().map_or(0, char::len_utf8);
+        start..end
+    }
+
+    // whether "range" of text starts at the start of a char's folding and ends at the end of one:
+    // a match can't take only some of the "ss" that "ß" folds to
+    fn is_whole_chars(&self, range: &Range<usize>) -> bool {
+        let boundary = |i: usize| i == 0 || i == self.starts.len() || self.starts[i] != self.starts[i - 1];
+        boundary(range.start) && boundary(range.end)
+    }
+
+    // the first match in text at or after "from" that is made of whole chars,
+    // asking "find" for the next one from the next char after any that isn't
+    fn find_whole<F>(&self, mut from: usize, find: F) -> Option<Range<usize>>
+    where
+        F: Fn(usize) -> Option<Range<usize>>,
+    {
+        loop {
+            let range = find(from)?;
+            if self.is_whole_chars(&range) {
+                return Some(range);
+            }
+            from = range.start + self.text[range.start..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+
+    // like find_whole, mapped back to the line
+    pub fn find_at<F>(&self, from: usize, find: F) -> Option<Range<usize>>
+    where
+        F: Fn(usize) -> Option<Range<usize>>,
+    {
+        self.find_whole(from, find).map(|range| self.original_range(range))
+    }
+
+    // the non-overlapping matches made of whole chars, mapped back to the line;
+    // "find" gives the first match in text at or after an offset
+    pub fn find_all<F>(&self, find: F) -> Vec<Range<usize>>
+    where
+        F: Fn(usize) -> Option<Range<usize>>,
+    {
+        let mut ranges = Vec::new();
+        let mut from = 0;
+        while let Some(range) = self.find_whole(from, &find) {
+            from = if range.is_empty() {
+                range.end + self.text[range.end..].chars().next().map_or(1, char::len_utf8)
+            } else {
+                range.end
+            };
+            ranges.push(self.original_range(range));
+            if from > self.text.len() {
+                break;
+            }
+        }
+        ranges
+    }
+}
+
+// byte ranges in "line" of the non-overlapping case insensitive matches of "folded_query",
+// which must already be folded with fold()
+pub fn find_folded(line: &str, folded_query: &str) -> Vec<Range<usize>> {
+    if line.is_ascii() {
+        // fast path: ASCII only folds to ASCII, so nothing needs to be allocated,
+        // and a query that folds to anything else can't match
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        while let Some(range) = find_ascii_at(line, folded_query, start) {
+            start = range.end;
+            ranges.push(range);
+            // an empty query matches once, at the start
+            if folded_query.is_empty() {
+                break;
+            }
+        }
+        return ranges;
+    }
+
+    let folded = Folded::new(line);
+    folded.find_all(|from| find_at(&folded.text, folded_query, from))
+}
+
+// like find_folded, true if there is at least one match
+pub fn contains_folded(line: &str, folded_query: &str) -> bool {
+    if line.is_ascii() {
+        return find_ascii_at(line, folded_query, 0).is_some();
+    }
+    let folded = Folded::new(line);
+    folded.find_at(0, |from| find_at(&folded.text, folded_query, from)).is_some()
+}
+
+// the first match of "query" in "text" at or after "from"
+pub fn find_at(text: &str, query: &str, from: usize) -> Option<Range<usize>> {
+    let i = from + text.get(from..)?.find(query)?;
+    Some(i..i + query.len())
+}
+
+// the first ASCII case insensitive match of "folded_query" in an ASCII "line" at or after "start"
+pub fn find_ascii_at(line: &str, folded_query: &str, start: usize) -> Option<Range<usize>> {
+    let (haystack, needle) = (line.as_bytes(), folded_query.as_bytes());
+    if !folded_query.is_ascii() || start > haystack.len() {
+        return None;
+    }
+    (start..=haystack.len().checked_sub(needle.len())?)
+        .find(|&i| haystack[i..i + needle.len()].eq_ignore_ascii_case(needle))
+        .map(|i| i..i + needle.len())
+}
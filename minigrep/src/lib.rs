@@ -1,32 +1,321 @@
-use std::fs;
+use std::fs::File;
 use std::error::Error;
+use std::borrow::Cow;
+use std::io::{self, BufRead, BufReader, Write};
+use std::iter;
+use std::ops::Range;
+use std::path::Path;
 
 pub mod config;
+pub mod decompress;
+pub mod encoding;
+pub mod fold;
+pub mod literal;
+pub mod matcher;
+pub mod mmap;
+pub mod parallel;
+pub mod printer;
+pub mod replace;
+pub mod stream;
+pub mod walk;
 
-use config::Config;
+use config::{BinaryFiles, Config, OutputMode};
+use encoding::Decoder;
+use literal::{LiteralLine, LiteralLines};
+use matcher::Matcher;
+use printer::Printer;
+use stream::BUFFER_SIZE;
+use walk::{Input, WalkOptions};
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> 
+// FILE argument that means "read standard input"
+pub const STDIN_PATH: &str = "-";
+// name used for standard input in output and error messages
+pub const STDIN_LABEL: &str = "(standard input)";
+
+// A matching line and where it was found.
+// "line" borrows from the searched string when searching in memory (search_matches),
+// and is owned when the line was read from a stream (search_reader).
+#[derive(Debug, PartialEq)]
+pub struct Match<'a> {
+    // 1-based line number
+    pub line_no: usize,
+    // byte offset of the start of the line in the input
+    pub byte_offset: usize,
+    // the line without its line ending
+    pub line: Cow<'a, str>,
+    // byte ranges of the matches within line, empty for lines selected by -v
+    pub ranges: Vec<Range<usize>>,
+    // true for lines printed as context around a match (-A, -B, -C)
+    pub context: bool,
+}
+
+impl Match<'_> {
+    // 1-based column (in bytes) of the first match in the line
+    pub fn column(&self) -> Option<usize> {
+        self.ranges.first().map(|range| range.start + 1)
+    }
+}
+
+// What happened in a run, so main can pick a grep-compatible exit status.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    // at least one line was selected
+    Matched,
+    // everything was searched and no line was selected
+    NoMatch,
+    // some inputs couldn't be searched; the others may or may not have matched
+    Errors { matched: bool, errors: usize },
+}
+
+impl Outcome {
+    // 0 if a line was selected, 1 if none was, 2 if there was an error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Matched => 0,
+            Outcome::NoMatch => 1,
+            Outcome::Errors { .. } => 2,
+        }
+    }
+}
+
+// Errors that stop the whole run (like an invalid regex) are returned as Err,
+// errors reading individual inputs are reported on stderr and counted in the Outcome.
+pub fn run(config: Config) -> Result<Outcome, Box<dyn Error>>
 {
-    let contents = fs::read_to_string(config.filename)?;
+    let matcher = Matcher::from_config(&config)?;
 
-    let matches = if config.case_sensitive {
-        search(&config.query, &contents)
+    // errors for individual files are reported as we go, without stopping the search
+    let mut errors = 0;
+    let mut matched = false;
+    let files = walk::walk(&config.filenames, &WalkOptions::from_config(&config), |path, err| {
+        eprintln!("minigrep: {}: {}", path.display(), err);
+        errors += 1;
+    });
+    let with_filename = files.len() > 1 || config.recursive;
+    let stdout = io::stdout();
+    let mut printer = Printer::new(&config, with_filename, stdout.lock());
+
+    // returns false when there is no point searching any more files
+    let mut record = |name: &str, result: io::Result<usize>| -> bool {
+        match result {
+            Ok(count) => matched |= count > 0,
+            // nobody is reading our output any more (e.g. piped into head),
+            // the lines selected before that are picked up from the printer below
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return false,
+            Err(err) => {
+                eprintln!("minigrep: {}: {}", name, err);
+                errors += 1;
+            }
+        }
+        // -q only needs to know that something matched
+        !(matched && config.output == OutputMode::Quiet)
+    };
+
+    if let (true, Some(replacement)) = (config.in_place, &config.replace) {
+        // files are rewritten one at a time, so --dry-run prints them in order
+        for input in &files {
+            let name = display_name(&input.path);
+            let result = replace::replace_in_file(&config, &matcher, replacement, input).and_then(|changes| {
+                if config.dry_run {
+                    printer.print_diff(&name, &changes)?;
+                }
+                Ok(changes.len())
+            });
+            if !record(&name, result) {
+                break;
+            }
+        }
+    } else if config.threads > 1 && files.len() > 1 {
+        // every file is printed to a buffer by a worker, then copied to stdout in order
+        parallel::for_each_file(
+            &files,
+            config.threads,
+            |input| {
+                let mut buffer = Printer::new(&config, with_filename, Vec::new());
+                let result = search_path(&config, &matcher, input, files.len(), &mut buffer);
+                (display_name(&input.path), buffer, result)
+            },
+            |(name, buffer, result)| {
+                let result = result.and_then(|count| printer.append(buffer).map(|_| count));
+                record(&name, result)
+            },
+        );
     } else {
-        search_case_insensitive(&config.query, &contents)
+        for input in &files {
+            let result = search_path(&config, &matcher, input, files.len(), &mut printer);
+            if !record(&display_name(&input.path), result) {
+                break;
+            }
+        }
+    }
+
+    // what was selected in a file even if printing it failed part way
+    matched |= printer.matched();
+    match printer.finish() {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err.into()),
+        _ => {}
+    }
+
+    Ok(if matched && config.output == OutputMode::Quiet {
+        // -q succeeds on a match even if there were errors
+        Outcome::Matched
+    } else if errors > 0 {
+        Outcome::Errors { matched, errors }
+    } else if matched {
+        Outcome::Matched
+    } else {
+        Outcome::NoMatch
+    })
+}
+
+// name of a FILE argument in output and error messages
+fn display_name(path: &Path) -> String {
+    if path.as_os_str() == STDIN_PATH {
+        String::from(STDIN_LABEL)
+    } else {
+        path.display().to_string()
+    }
+}
+
+// searches one file (or stdin) and prints the results,
+// returning the number of selected lines
+// "files" is the number of files searched in the run, which decides whether mapping files is worth it
+fn search_path<W: Write>(config: &Config, matcher: &Matcher, input: &Input, files: usize, printer: &mut Printer<W>) -> io::Result<usize> {
+    let path = &input.path;
+    let name = display_name(path);
+
+    // context lines are only needed when lines are printed
+    let (before, after) = if config.output == OutputMode::Lines {
+        (config.before_context, config.after_context)
+    } else {
+        (0, 0)
     };
 
-    for line in matches {
-        println!("{}", line);
+    // both stdin and files are searched line by line as they are read,
+    // so memory use doesn't depend on the size of the input
+    if path.as_os_str() == STDIN_PATH {
+        let stdin = io::stdin();
+        let reader = BufReader::with_capacity(BUFFER_SIZE, stdin.lock());
+        search_input(config, matcher, &name, input, reader, (before, after), printer)
+    } else {
+        let file = File::open(path)?;
+        if let Some(map) = mmap::map(&file, files) {
+            return search_mapped(config, matcher, &name, input, &map, (before, after), printer);
+        }
+        let reader = BufReader::with_capacity(BUFFER_SIZE, file);
+        search_input(config, matcher, &name, input, reader, (before, after), printer)
     }
+}
 
-    Ok(())
+// decompresses (-z) and decodes (--encoding) an input before searching it
+fn search_input<R: BufRead, W: Write>(
+    config: &Config,
+    matcher: &Matcher,
+    name: &str,
+    input: &Input,
+    mut reader: R,
+    context: (usize, usize),
+    printer: &mut Printer<W>,
+) -> io::Result<usize> {
+    let format = if config.search_zip { decompress::detect(&input.path, reader.fill_buf()?) } else { None };
+    match format {
+        Some(format) => {
+            let reader = BufReader::with_capacity(BUFFER_SIZE, decompress::decompress(reader, format)?);
+            let reader = Decoder::new(reader, config.encoding)?;
+            search_buffered(config, matcher, name, input.walked, reader, context, printer)
+        }
+        None => {
+            let reader = Decoder::new(reader, config.encoding)?;
+            search_buffered(config, matcher, name, input.walked, reader, context, printer)
+        }
+    }
+}
+
+fn search_buffered<R: BufRead, W: Write>(
+    config: &Config,
+    matcher: &Matcher,
+    name: &str,
+    walked: bool,
+    mut reader: R,
+    (before, after): (usize, usize),
+    printer: &mut Printer<W>,
+) -> io::Result<usize> {
+    // like grep, an input is taken to be binary if its first buffer holds a NUL byte
+    // (after decoding, since UTF-16 text is full of them)
+    let binary = config.binary_files != BinaryFiles::Text && reader.fill_buf()?.contains(&0);
+    let matches = search_reader(matcher, reader)
+        .invert(config.invert_match)
+        .context(before, after);
+    print_matches(config, matcher, name, walked, binary, matches, printer)
+}
+
+// A file mapped into memory. Plain UTF-8 text is searched where it is, like a string
+// with search_matches (so the literal fast path can be used), and the matching lines
+// borrow from the mapping. Everything else is streamed from the mapping like from a file.
+fn search_mapped<W: Write>(
+    config: &Config,
+    matcher: &Matcher,
+    name: &str,
+    input: &Input,
+    bytes: &[u8],
+    context: (usize, usize),
+    printer: &mut Printer<W>,
+) -> io::Result<usize> {
+    let compressed = config.search_zip && decompress::detect(&input.path, bytes).is_some();
+    // context and -v need the lines around the matches, which only stream::Matches keeps track of
+    let text = if compressed || context != (0, 0) || config.invert_match {
+        None
+    } else {
+        encoding::as_utf8(bytes, config.encoding)
+    };
+    match text {
+        Some(text) => {
+            let head = &text.as_bytes()[..text.len().min(BUFFER_SIZE)];
+            let binary = config.binary_files != BinaryFiles::Text && head.contains(&0);
+            let matches = iter_matches(matcher, text).map(Ok);
+            print_matches(config, matcher, name, input.walked, binary, matches, printer)
+        }
+        None => search_input(config, matcher, name, input, bytes, context, printer),
+    }
+}
+
+// prints the matches of an input, as binary (see search_buffered) or not, applying --replace
+// binary files found by walking a directory are skipped without a word, like grep -r
+fn print_matches<'a, I, W>(
+    config: &Config,
+    matcher: &Matcher,
+    name: &str,
+    walked: bool,
+    binary: bool,
+    matches: I,
+    printer: &mut Printer<W>,
+) -> io::Result<usize>
+where
+    I: Iterator<Item = io::Result<Match<'a>>>,
+    W: Write,
+{
+    if binary && walked {
+        return Ok(0);
+    }
+    if binary && config.binary_files == BinaryFiles::WithoutMatch {
+        return printer.print_file(name, iter::empty());
+    }
+    let matches = matches.map(|result| match &config.replace {
+        Some(replacement) => result.map(|m| replace::replace_match(matcher, replacement, m)),
+        None => result,
+    });
+    if binary {
+        printer.print_binary_file(name, matches)
+    } else {
+        printer.print_file(name, matches)
+    }
 }
 
 // lifetime parameter 'a
 // tells Rust that this function returns a Vector of slices whose lifetime matches the lifetime of the "contents" string
 pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
 
-    /* 
+    /*
     let mut matches = Vec::new();
 
     for line in contents.lines() {
@@ -38,15 +327,14 @@ pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
     matches
     */
 
-    contents.lines()
-        .filter(|line| line.contains(query))
-        .collect()
+    search_with(&Matcher::substring(query, true), contents)
 }
 
 pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
 
+    /*
+    // to_lowercase isn't full Unicode case folding, see fold.rs
     let query = query.to_lowercase();
-    /* 
     let mut matches = Vec::new();
 
     for line in contents.lines() {
@@ -58,7 +346,52 @@ pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a st
     matches
     */
 
+    search_with(&Matcher::substring(query, false), contents)
+}
+
+// common code path for substring and regex search
+pub fn search_with<'a>(matcher: &Matcher, contents: &'a str) -> Vec<&'a str> {
+    if let Some(needle) = matcher.literal() {
+        return LiteralLines::new(needle, contents).map(|(_, _, line)| line).collect();
+    }
+
     contents.lines()
-        .filter(|line| line.to_lowercase().contains(&query))
+        .filter(|line| matcher.is_match(line))
         .collect()
-}
\ No newline at end of file
+}
+
+// like search_with, but also reports where each line was found and where it matched
+pub fn search_matches<'a>(matcher: &Matcher, contents: &'a str) -> Vec<Match<'a>> {
+    iter_matches(matcher, contents).collect()
+}
+
+// search_matches, finding the matches one at a time as the iterator is advanced
+fn iter_matches<'m, 'a: 'm>(matcher: &'m Matcher, contents: &'a str) -> Box<dyn Iterator<Item = Match<'a>> + 'm> {
+    let to_match = move |(line_no, byte_offset, line): LiteralLine<'a>| Match {
+        line_no,
+        byte_offset,
+        line: Cow::Borrowed(line),
+        ranges: matcher.find_ranges(line),
+        context: false,
+    };
+
+    if let Some(needle) = matcher.literal() {
+        return Box::new(LiteralLines::new(needle, contents).map(to_match));
+    }
+
+    let mut byte_offset = 0;
+    // split_inclusive keeps the line endings, so the byte offsets stay correct
+    let lines = contents.split_inclusive('\n').enumerate().filter_map(move |(i, line)| {
+        let line_offset = byte_offset;
+        byte_offset += line.len();
+        let line = line.strip_suffix('\n').map_or(line, |l| l.strip_suffix('\r').unwrap_or(l));
+        matcher.is_match(line).then(|| to_match((i + 1, line_offset, line)))
+    });
+    Box::new(lines)
+}
+
+// streaming version of search_matches for inputs that shouldn't be loaded into memory
+// matches are read from "reader" one line at a time as the iterator is advanced
+pub fn search_reader<R: BufRead>(matcher: &Matcher, reader: R) -> stream::Matches<'_, R> {
+    stream::Matches::new(reader, matcher)
+}
@@ -0,0 +1,57 @@
+use memchr::{memchr, memchr_iter, memrchr, memmem};
+
+// Fast path for plain, case sensitive queries.
+// Instead of splitting the input into lines and calling contains() on every one,
+// the whole buffer is searched for the needle with memchr's SIMD accelerated memmem,
+// and line boundaries are only looked for around each hit.
+// Lines without a match are never looked at one by one.
+pub struct LiteralLines<'n, 'h> {
+    finder: memmem::Finder<'n>,
+    haystack: &'h str,
+    // where to search for the next hit, always the start of a line
+    pos: usize,
+    // line number of the line starting at pos
+    line_no: usize,
+}
+
+// a line found by LiteralLines: (1-based line number, byte offset of the line, line)
+pub type LiteralLine<'h> = (usize, usize, &'h str);
+
+impl<'n, 'h> LiteralLines<'n, 'h> {
+    // the needle can't be empty or contain a line ending,
+    // since a hit would then not be inside a single line (see Matcher::literal)
+    pub fn new(needle: &'n str, haystack: &'h str) -> LiteralLines<'n, 'h> {
+        debug_assert!(!needle.is_empty() && !needle.contains(['\n', '\r']));
+        LiteralLines {
+            finder: memmem::Finder::new(needle.as_bytes()),
+            haystack,
+            pos: 0,
+            line_no: 1,
+        }
+    }
+}
+
+impl<'h> Iterator for LiteralLines<'_, 'h> {
+    type Item = LiteralLine<'h>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.haystack.as_bytes();
+        let hit = self.pos + self.finder.find(&bytes[self.pos..])?;
+
+        // widen the hit to the line around it
+        let start = memrchr(b'\n', &bytes[self.pos..hit]).map_or(self.pos, |i| self.pos + i + 1);
+        let end = memchr(b'\n', &bytes[hit..]).map_or(bytes.len(), |i| hit + i);
+
+        // line numbers only need the newlines between the last line and this one to be counted
+        self.line_no += memchr_iter(b'\n', &bytes[self.pos..start]).count();
+        let line_no = self.line_no;
+
+        self.pos = (end + 1).min(bytes.len());
+        self.line_no += 1;
+
+        // the same line str::lines() would give: without "\n" or "\r\n"
+        let line = &self.haystack[start..end];
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        Some((line_no, start, line))
+    }
+}
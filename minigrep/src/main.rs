@@ -1,19 +1,34 @@
 use std::env;
 use std::process;
 
-use minigrep::config::Config;
+use minigrep::config::{ArgsError, Config};
+
+// exit status for usage errors and errors that stop the run, like grep
+const EXIT_ERROR: i32 = 2;
 
 fn main() {
     //let args: Vec<String> = env::args().collect();
     let args = env::args(); // iter
 
     let config = Config::parse_args(args).unwrap_or_else(|err| {
-        eprintln!("Problem parsing command line: {}", err);
-        process::exit(1);
+        match err {
+            ArgsError::Help | ArgsError::Version => {
+                println!("{}", err);
+                process::exit(0);
+            }
+            ArgsError::Usage(_) => {
+                eprintln!("Problem parsing command line: {}", err);
+                eprintln!("Try 'minigrep --help' for more information.");
+                process::exit(EXIT_ERROR);
+            }
+        }
     });
 
-    if let Err(e) = minigrep::run(config) {
-        eprintln!("Application error: {}", e);
-        process::exit(1);
+    match minigrep::run(config) {
+        Ok(outcome) => process::exit(outcome.exit_code()),
+        Err(e) => {
+            eprintln!("Application error: {}", e);
+            process::exit(EXIT_ERROR);
+        }
     }
 }
@@ -0,0 +1,294 @@
+use std::error::Error;
+use std::ops::Range;
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, Input, MatchKind};
+use regex::{Captures, Regex, RegexBuilder};
+
+use crate::config::Config;
+use crate::fold;
+
+// A Matcher decides whether a single line matches the query.
+// run() and the search functions all go through a Matcher,
+// so substring and regex matching share the same code path.
+pub enum Matcher {
+    // plain "contains" matching
+    // for case insensitive search the query is stored case folded
+    Substring { query: String, case_sensitive: bool },
+    // several plain patterns (-e, -f), a line matches if any of them does
+    // all the patterns are searched for in a single pass with Aho-Corasick,
+    // for case insensitive search the automaton is built from the case folded patterns
+    Multi { patterns: AhoCorasick, case_sensitive: bool },
+    // the query compiled as a regular expression
+    // case insensitivity is handled by the compiled pattern itself
+    // with several patterns, each one is also compiled on its own, so that --replace can
+    // number capture groups within the pattern that matched
+    Regex { re: Regex, patterns: Vec<Regex> },
+    // another matcher, keeping only the matches that are whole words (-w) or the whole line (-x)
+    Bounded(Box<Matcher>, Boundary),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary {
+    // not preceded or followed by a word character
+    Word,
+    // from the start to the end of the line
+    Line,
+}
+
+impl Matcher {
+    pub fn substring(query: &str, case_sensitive: bool) -> Matcher {
+        let query = if case_sensitive {
+            query.to_string()
+        } else {
+            fold::fold(query)
+        };
+        Matcher::Substring { query, case_sensitive }
+    }
+
+    pub fn multi(patterns: &[String], case_sensitive: bool) -> Result<Matcher, aho_corasick::BuildError> {
+        let patterns: Vec<String> = if case_sensitive {
+            patterns.to_vec()
+        } else {
+            patterns.iter().map(|p| fold::fold(p)).collect()
+        };
+        let patterns = AhoCorasickBuilder::new()
+            // leftmost-longest gives the same non-overlapping matches grep highlights
+            .match_kind(MatchKind::LeftmostLongest)
+            // lets ASCII lines be searched without folding them first
+            .ascii_case_insensitive(!case_sensitive)
+            .build(&patterns)?;
+        Ok(Matcher::Multi { patterns, case_sensitive })
+    }
+
+    pub fn regex(pattern: &str, case_sensitive: bool) -> Result<Matcher, regex::Error> {
+        Matcher::regexes(&[pattern.to_string()], case_sensitive)
+    }
+
+    // several regular expressions, combined into a single alternation
+    pub fn regexes(patterns: &[String], case_sensitive: bool) -> Result<Matcher, regex::Error> {
+        let pattern = if patterns.is_empty() {
+            // no patterns (an empty -f file) match nothing
+            String::from(r"[^\s\S]")
+        } else {
+            patterns.iter().map(|p| format!("(?:{})", p)).collect::<Vec<_>>().join("|")
+        };
+        let build = |pattern: &str| RegexBuilder::new(pattern).case_insensitive(!case_sensitive).build();
+        let re = build(&pattern)?;
+        let patterns = if patterns.len() > 1 {
+            patterns.iter().map(|p| build(p)).collect::<Result<_, _>>()?
+        } else {
+            Vec::new()
+        };
+        Ok(Matcher::Regex { re, patterns })
+    }
+
+    pub fn bounded(matcher: Matcher, boundary: Boundary) -> Matcher {
+        Matcher::Bounded(Box::new(matcher), boundary)
+    }
+
+    pub fn from_config(config: &Config) -> Result<Matcher, Box<dyn Error>> {
+        let matcher = if config.regex && config.line_regexp {
+            // anchoring the regex lets it try every way of matching the whole line,
+            // the first match it finds on its own might be shorter
+            let anchored: Vec<String> = config.patterns.iter().map(|p| format!("^(?:{})$", p)).collect();
+            Matcher::regexes(&anchored, config.case_sensitive)?
+        } else if config.regex && config.word_regexp {
+            // the same goes for -w: filtering the leftmost matches would miss whole words
+            // behind them ("foo|foobar" in "foobar", "a a" in "xa a a")
+            // \b{start-half} and \b{end-half} only look at the side outside the match, like grep
+            let bounded: Vec<String> = config.patterns.iter()
+                .map(|p| format!(r"\b{{start-half}}(?:{})\b{{end-half}}", p))
+                .collect();
+            return Ok(Matcher::regexes(&bounded, config.case_sensitive)?);
+        } else if config.regex {
+            Matcher::regexes(&config.patterns, config.case_sensitive)?
+        } else if config.patterns.len() == 1 {
+            Matcher::substring(&config.patterns[0], config.case_sensitive)
+        } else {
+            Matcher::multi(&config.patterns, config.case_sensitive)?
+        };
+
+        // -x wins over -w, like grep
+        if config.line_regexp {
+            Ok(Matcher::bounded(matcher, Boundary::Line))
+        } else if config.word_regexp {
+            Ok(Matcher::bounded(matcher, Boundary::Word))
+        } else {
+            Ok(matcher)
+        }
+    }
+
+    // the query if this is a plain case sensitive substring search that
+    // can use the literal fast path (see literal.rs)
+    pub fn literal(&self) -> Option<&str> {
+        match self {
+            Matcher::Substring { query, case_sensitive: true }
+                if !query.is_empty() && !query.contains(['\n', '\r']) => Some(query),
+            _ => None,
+        }
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        match self {
+            Matcher::Substring { query, case_sensitive: true } => line.contains(query.as_str()),
+            Matcher::Substring { query, case_sensitive: false } => fold::contains_folded(line, query),
+            Matcher::Multi { patterns, case_sensitive } => {
+                if *case_sensitive || line.is_ascii() {
+                    patterns.is_match(line)
+                } else {
+                    let folded = fold::Folded::new(line);
+                    folded.find_at(0, |from| find_multi(patterns, &folded.text, from)).is_some()
+                }
+            }
+            Matcher::Regex { re, .. } => re.is_match(line),
+            // the quick check rules out most lines before looking at every match
+            Matcher::Bounded(inner, _) => inner.is_match(line) && !self.find_ranges(line).is_empty(),
+        }
+    }
+
+    // byte ranges of all the non-overlapping matches in line, in order
+    pub fn find_ranges(&self, line: &str) -> Vec<Range<usize>> {
+        match self {
+            Matcher::Substring { query, case_sensitive: true } => line
+                .match_indices(query.as_str())
+                .map(|(start, m)| start..start + m.len())
+                .collect(),
+            Matcher::Substring { query, case_sensitive: false } => fold::find_folded(line, query),
+            Matcher::Multi { patterns, case_sensitive } => {
+                if *case_sensitive || line.is_ascii() {
+                    patterns.find_iter(line).map(|m| m.range()).collect()
+                } else {
+                    let folded = fold::Folded::new(line);
+                    folded.find_all(|from| find_multi(patterns, &folded.text, from))
+                }
+            }
+            Matcher::Regex { re, .. } => re.find_iter(line).map(|m| m.range()).collect(),
+            Matcher::Bounded(inner, Boundary::Word) => {
+                // a match that isn't a whole word can hide one that is ("a a" in "xa a a"),
+                // so after a failed match the search starts again from its next char
+                let finder = inner.finder(line);
+                let mut ranges = Vec::new();
+                let mut start = 0;
+                while let Some(range) = finder.find_at(start) {
+                    if is_word_boundary(line, &range) {
+                        start = if range.is_empty() { next_char(line, range.end) } else { range.end };
+                        ranges.push(range);
+                    } else {
+                        start = next_char(line, range.start);
+                    }
+                    if start > line.len() {
+                        break;
+                    }
+                }
+                ranges
+            }
+            Matcher::Bounded(inner, Boundary::Line) => inner
+                .find_ranges(line)
+                .into_iter()
+                .filter(|range| range.start == 0 && range.end == line.len())
+                .collect(),
+        }
+    }
+
+    // searches "line" from any offset, folding it at most once
+    fn finder<'l>(&'l self, line: &'l str) -> Finder<'l> {
+        let folds = match self {
+            Matcher::Substring { case_sensitive, .. } | Matcher::Multi { case_sensitive, .. } => !*case_sensitive,
+            _ => false,
+        };
+        let folded = (folds && !line.is_ascii()).then(|| fold::Folded::new(line));
+        Finder { matcher: self, line, folded }
+    }
+
+    // --replace: "line" with the matches at "ranges" (from find_ranges) replaced,
+    // and the ranges of the replacements in the new line.
+    // For a regex, $1, ${name} and $$ in "replacement" refer to the match's capture groups,
+    // otherwise the replacement is used as it is.
+    pub fn replace(&self, line: &str, ranges: &[Range<usize>], replacement: &str) -> (String, Vec<Range<usize>>) {
+        let mut replaced = String::with_capacity(line.len());
+        let mut new_ranges = Vec::with_capacity(ranges.len());
+        let mut last = 0;
+        for range in ranges {
+            replaced.push_str(&line[last..range.start]);
+            let start = replaced.len();
+            match self.captures(line, range) {
+                Some(captures) => captures.expand(replacement, &mut replaced),
+                None => replaced.push_str(replacement),
+            }
+            new_ranges.push(start..replaced.len());
+            last = range.end;
+        }
+        replaced.push_str(&line[last..]);
+        (replaced, new_ranges)
+    }
+
+    // the capture groups of the regex match at "range",
+    // numbered within the pattern that matched when there are several
+    fn captures<'l>(&self, line: &'l str, range: &Range<usize>) -> Option<Captures<'l>> {
+        match self {
+            // searching again from the start of the match finds the same match, with its captures
+            Matcher::Regex { re, patterns } if patterns.is_empty() => re.captures_at(line, range.start),
+            // the alternation takes the first pattern that matches there
+            Matcher::Regex { patterns, .. } => patterns
+                .iter()
+                .filter_map(|re| re.captures_at(line, range.start))
+                .find(|captures| captures.get(0).is_some_and(|m| m.range() == *range)),
+            Matcher::Bounded(inner, _) => inner.captures(line, range),
+            _ => None,
+        }
+    }
+}
+
+// A line prepared for looking for matches from any offset (see Matcher::finder),
+// so -w can start again after a match that isn't a whole word without searching
+// the rest of the line for every match each time.
+struct Finder<'l> {
+    matcher: &'l Matcher,
+    line: &'l str,
+    // the folded line, for case insensitive plain patterns on a line that isn't ASCII
+    folded: Option<fold::Folded<'l>>,
+}
+
+impl Finder<'_> {
+    // the first match starting at or after "start", which must be on a char boundary
+    fn find_at(&self, start: usize) -> Option<Range<usize>> {
+        let line = self.line;
+        match (self.matcher, &self.folded) {
+            (Matcher::Substring { query, case_sensitive: true }, _) => {
+                line[start..].find(query.as_str()).map(|i| start + i..start + i + query.len())
+            }
+            (Matcher::Substring { query, .. }, None) => fold::find_ascii_at(line, query, start),
+            (Matcher::Substring { query, .. }, Some(folded)) => {
+                folded.find_at(folded.folded_offset(start), |from| fold::find_at(&folded.text, query, from))
+            }
+            (Matcher::Multi { patterns, .. }, None) => find_multi(patterns, line, start),
+            (Matcher::Multi { patterns, .. }, Some(folded)) => {
+                folded.find_at(folded.folded_offset(start), |from| find_multi(patterns, &folded.text, from))
+            }
+            (Matcher::Regex { re, .. }, _) => re.find_at(line, start).map(|m| m.range()),
+            // from_config never puts a Bounded matcher inside another one
+            (Matcher::Bounded(..), _) => {
+                let range = self.matcher.find_ranges(&line[start..]).into_iter().next()?;
+                Some(range.start + start..range.end + start)
+            }
+        }
+    }
+}
+
+// the first match of any of "patterns" in "text" at or after "from"
+fn find_multi(patterns: &AhoCorasick, text: &str, from: usize) -> Option<Range<usize>> {
+    patterns.find(Input::new(text).span(from..text.len())).map(|m| m.range())
+}
+
+// the offset of the char after the one at "i", or past the end of line
+fn next_char(line: &str, i: usize) -> usize {
+    i + line[i..].chars().next().map_or(1, char::len_utf8)
+}
+
+// -w: the chars either side of the match (if any) aren't letters, digits or '_'
+fn is_word_boundary(line: &str, range: &Range<usize>) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let before = line[..range.start].chars().next_back();
+    let after = line[range.end..].chars().next();
+    !before.is_some_and(is_word) && !after.is_some_and(is_word)
+}
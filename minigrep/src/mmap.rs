@@ -0,0 +1,29 @@
+use std::fs::File;
+
+use memmap2::Mmap;
+
+use crate::stream::BUFFER_SIZE;
+
+// Files smaller than this are read in a few reads anyway, and mapping them costs more than it saves.
+pub const MIN_LEN: u64 = 4 * BUFFER_SIZE as u64;
+// Mapping and unmapping a file is slow compared to reading a small one, so when many files
+// are searched (usually with -r) buffered reads are faster overall.
+pub const MAX_FILES: usize = 16;
+
+// Maps "file" into memory if it is worth it: a regular file of at least MIN_LEN bytes,
+// when no more than MAX_FILES files are searched in the run.
+// Returns None if it isn't, or if mapping fails (e.g. on a file system that doesn't support it);
+// the file is then read with a buffer instead.
+pub fn map(file: &File, files: usize) -> Option<Mmap> {
+    if files > MAX_FILES {
+        return None;
+    }
+    let metadata = file.metadata().ok()?;
+    if !metadata.is_file() || metadata.len() < MIN_LEN {
+        return None;
+    }
+    // Safety: the mapping is only read from. If another process truncates or changes the file
+    // while it is searched the results can be wrong or the process can get SIGBUS;
+    // grep tools that use mmap (like ripgrep) accept this risk for the speed.
+    unsafe { Mmap::map(file) }.ok()
+}
@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+// Runs "search" for every file on a pool of "threads" worker threads.
+// Workers take the next file from a shared counter and send back the result,
+// which for minigrep holds everything printed for that file.
+// Results are handed to "on_result" on the calling thread in the same order as "files",
+// so the output is the same as a sequential search and files never interleave.
+// on_result returns false to stop early (-q); files already being searched are finished first.
+pub fn for_each_file<T, S, R, F>(files: &[T], threads: usize, search: S, mut on_result: F)
+where
+    T: Sync,
+    S: Fn(&T) -> R + Sync,
+    R: Send,
+    F: FnMut(R) -> bool,
+{
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..threads.min(files.len()) {
+            let tx = tx.clone();
+            let (next, stop, search) = (&next, &stop, &search);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= files.len() {
+                        break;
+                    }
+                    if tx.send((i, search(&files[i]))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // the workers hold the only senders left, so rx ends when they have all finished
+        drop(tx);
+
+        // results that arrived before the ones in front of them
+        let mut pending = BTreeMap::new();
+        let mut next_result = 0;
+        for (i, result) in rx {
+            pending.insert(i, result);
+            while let Some(result) = pending.remove(&next_result) {
+                next_result += 1;
+                if !on_result(result) {
+                    stop.store(true, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+    });
+}
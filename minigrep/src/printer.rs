@@ -0,0 +1,275 @@
+use std::borrow::Cow;
+use std::io::{self, IsTerminal, Write};
+
+use serde_json::json;
+
+use crate::config::{ColorChoice, Config, OutputMode};
+use crate::replace::Change;
+use crate::Match;
+
+// ANSI escape codes, using grep's default colors
+const MATCH_COLOR: &str = "\x1b[1;31m";
+const PATH_COLOR: &str = "\x1b[35m";
+const NUMBER_COLOR: &str = "\x1b[32m";
+const SEPARATOR_COLOR: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+// Prints the results of a search to "out" according to the output options in Config.
+// One Printer is used for all the files of a run, because some output
+// (like the "--" between context groups) depends on what was printed before.
+// When files are searched in parallel, each file is printed to a buffer by its own Printer
+// and then appended to the main one.
+pub struct Printer<'c, W> {
+    config: &'c Config,
+    out: W,
+    // prefix lines with "path:" when searching more than one file
+    with_filename: bool,
+    // line number of the last line printed for the current file
+    last_line_no: Option<usize>,
+    // true once any line has been printed, in any file
+    printed: bool,
+    // highlight output with ANSI escape codes
+    color: bool,
+    // totals for the --json summary
+    files: usize,
+    files_matched: usize,
+    lines: usize,
+}
+
+impl<'c, W: Write> Printer<'c, W> {
+    pub fn new(config: &'c Config, with_filename: bool, out: W) -> Printer<'c, W> {
+        Printer {
+            config,
+            out,
+            with_filename,
+            last_line_no: None,
+            printed: false,
+            // --color=auto only colors when a person is looking at the output
+            color: match config.color {
+                ColorChoice::Always => true,
+                ColorChoice::Never => false,
+                ColorChoice::Auto => io::stdout().is_terminal(),
+            },
+            files: 0,
+            files_matched: 0,
+            lines: 0,
+        }
+    }
+
+    // prints the matches of one input and returns how many lines were selected
+    // (context lines aren't counted)
+    pub fn print_file<'a, I>(&mut self, name: &str, matches: I) -> io::Result<usize>
+    where
+        I: Iterator<Item = io::Result<Match<'a>>>,
+    {
+        let config = self.config;
+        self.last_line_no = None;
+
+        if config.json {
+            self.print_json(json!({"type": "begin", "data": {"path": name}}))?;
+        }
+        let mut count = 0;
+        for result in matches {
+            let m = result?;
+            if !m.context {
+                // counted before printing, so matched() knows about it even if printing fails
+                count += 1;
+                self.lines += 1;
+            }
+            if config.json {
+                self.print_json_line(name, &m)?;
+            } else if config.output == OutputMode::Lines && config.only_matching {
+                self.print_only_matching(name, &m)?;
+            } else if config.output == OutputMode::Lines {
+                self.print_line(name, &m)?;
+            }
+            if !m.context && config.output > OutputMode::Count {
+                // one match is enough to decide for -l, -L and -q
+                break;
+            }
+        }
+
+        match config.output {
+            OutputMode::Lines | OutputMode::Quiet => {}
+            OutputMode::Count if self.with_filename => {
+                let (name, sep) = (self.paint(name, PATH_COLOR), self.paint(":", SEPARATOR_COLOR));
+                writeln!(self.out, "{}{}{}", name, sep, count)?
+            }
+            OutputMode::Count => writeln!(self.out, "{}", count)?,
+            OutputMode::FilesWithMatches if count > 0 => writeln!(self.out, "{}", self.paint(name, PATH_COLOR))?,
+            OutputMode::FilesWithoutMatch if count == 0 => writeln!(self.out, "{}", self.paint(name, PATH_COLOR))?,
+            OutputMode::FilesWithMatches | OutputMode::FilesWithoutMatch => {}
+        }
+        if config.json {
+            self.print_json(json!({"type": "end", "data": {"path": name, "matches": count}}))?;
+        }
+        self.files += 1;
+        self.files_matched += usize::from(count > 0);
+        Ok(count)
+    }
+
+    // like print_file for a file that looks binary (--binary-files=binary):
+    // instead of printing matching lines, which could mess up the terminal,
+    // only says whether the file matches
+    pub fn print_binary_file<'a, I>(&mut self, name: &str, matches: I) -> io::Result<usize>
+    where
+        I: Iterator<Item = io::Result<Match<'a>>>,
+    {
+        if self.config.output != OutputMode::Lines || self.config.json {
+            // -c, -l, -L and -q don't print lines anyway, and JSON escapes them
+            return self.print_file(name, matches);
+        }
+        for result in matches {
+            if !result?.context {
+                writeln!(self.out, "Binary file {} matches", name)?;
+                return Ok(1);
+            }
+        }
+        Ok(0)
+    }
+
+    // --dry-run: the changes --in-place would make to a file, as a unified diff
+    pub fn print_diff(&mut self, name: &str, changes: &[Change]) -> io::Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.out, "{}", self.paint(&format!("--- {}", name), PATH_COLOR))?;
+        writeln!(self.out, "{}", self.paint(&format!("+++ {}", name), PATH_COLOR))?;
+        for change in changes {
+            let hunk = format!("@@ -{} +{} @@", change.line_no, change.line_no);
+            writeln!(self.out, "{}", self.paint(&hunk, SEPARATOR_COLOR))?;
+            writeln!(self.out, "{}", self.paint(&format!("-{}", change.old), MATCH_COLOR))?;
+            writeln!(self.out, "{}", self.paint(&format!("+{}", change.new), NUMBER_COLOR))?;
+        }
+        Ok(())
+    }
+
+    // appends what another Printer printed to a buffer, as if this Printer had printed it
+    pub fn append(&mut self, other: Printer<'c, Vec<u8>>) -> io::Result<()> {
+        self.files += other.files;
+        self.files_matched += other.files_matched;
+        self.lines += other.lines;
+        if other.printed {
+            // other didn't know what came before it
+            self.separate_group()?;
+            self.printed = true;
+        }
+        self.out.write_all(&other.out)
+    }
+
+    // true if a line has been selected in any file so far, even if printing it failed
+    pub fn matched(&self) -> bool {
+        self.lines > 0
+    }
+
+    // to be called once every file has been printed
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.config.json {
+            let data = json!({"files": self.files, "files_with_matches": self.files_matched, "matches": self.lines});
+            self.print_json(json!({"type": "summary", "data": data}))?;
+        }
+        self.out.flush()
+    }
+
+    // "--" between groups of lines that aren't next to each other, if there is context
+    fn separate_group(&mut self) -> io::Result<()> {
+        let config = self.config;
+        if self.printed && (config.before_context > 0 || config.after_context > 0) {
+            writeln!(self.out, "{}", self.paint("--", SEPARATOR_COLOR))?;
+        }
+        Ok(())
+    }
+
+    fn print_line(&mut self, name: &str, m: &Match) -> io::Result<()> {
+        let contiguous = self.last_line_no.is_some_and(|last| last + 1 == m.line_no);
+        if !contiguous {
+            self.separate_group()?;
+        }
+        self.last_line_no = Some(m.line_no);
+        self.printed = true;
+
+        let fields = self.fields(name, m.line_no, m.column(), m.byte_offset, m.context);
+        writeln!(self.out, "{}{}", fields, self.highlight(m))
+    }
+
+    // -o: every match in a selected line on a line of its own, with its own column and byte offset
+    // (context lines and empty matches aren't printed, like grep)
+    fn print_only_matching(&mut self, name: &str, m: &Match) -> io::Result<()> {
+        if m.context {
+            return Ok(());
+        }
+        for range in m.ranges.iter().filter(|range| !range.is_empty()) {
+            let fields = self.fields(name, m.line_no, Some(range.start + 1), m.byte_offset + range.start, false);
+            writeln!(self.out, "{}{}", fields, self.paint(&m.line[range.clone()], MATCH_COLOR))?;
+        }
+        Ok(())
+    }
+
+    // the fields before the text of a line, in the order path:line:column:offset:,
+    // separated by '-' instead of ':' for context lines, like grep
+    fn fields(&self, name: &str, line_no: usize, column: Option<usize>, byte_offset: usize, context: bool) -> String {
+        let config = self.config;
+        let sep = self.paint(if context { "-" } else { ":" }, SEPARATOR_COLOR);
+        let mut fields = String::new();
+        if self.with_filename {
+            fields.push_str(&format!("{}{}", self.paint(name, PATH_COLOR), sep));
+        }
+        if config.line_number {
+            fields.push_str(&format!("{}{}", self.paint(&line_no.to_string(), NUMBER_COLOR), sep));
+        }
+        if config.column {
+            if let Some(column) = column {
+                fields.push_str(&format!("{}{}", self.paint(&column.to_string(), NUMBER_COLOR), sep));
+            }
+        }
+        if config.byte_offset {
+            fields.push_str(&format!("{}{}", self.paint(&byte_offset.to_string(), NUMBER_COLOR), sep));
+        }
+        fields
+    }
+
+    // --json: a "match" record for a selected line, "context" for a context line
+    fn print_json_line(&mut self, name: &str, m: &Match) -> io::Result<()> {
+        let submatches: Vec<_> = m.ranges.iter()
+            .map(|range| json!({"match": &m.line[range.clone()], "start": range.start, "end": range.end}))
+            .collect();
+        let data = json!({
+            "path": name,
+            "line_number": m.line_no,
+            "byte_offset": m.byte_offset,
+            "line": m.line,
+            "submatches": submatches,
+        });
+        self.print_json(json!({"type": if m.context { "context" } else { "match" }, "data": data}))
+    }
+
+    // one JSON object per line (JSON Lines)
+    fn print_json(&mut self, record: serde_json::Value) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &record)?;
+        writeln!(self.out)
+    }
+
+    // the line with every match painted in MATCH_COLOR
+    fn highlight<'m>(&self, m: &'m Match) -> Cow<'m, str> {
+        if !self.color || m.ranges.is_empty() {
+            return Cow::Borrowed(&m.line);
+        }
+        let mut line = String::with_capacity(m.line.len() + m.ranges.len() * 16);
+        let mut last = 0;
+        for range in &m.ranges {
+            line.push_str(&m.line[last..range.start]);
+            line.push_str(&self.paint(&m.line[range.clone()], MATCH_COLOR));
+            last = range.end;
+        }
+        line.push_str(&m.line[last..]);
+        Cow::Owned(line)
+    }
+
+    fn paint<'s>(&self, text: &'s str, color: &str) -> Cow<'s, str> {
+        if self.color && !text.is_empty() {
+            Cow::Owned(format!("{}{}{}", color, text, RESET))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+}
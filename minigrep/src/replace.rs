@@ -0,0 +1,84 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use tempfile::NamedTempFile;
+
+use crate::config::{BinaryFiles, Config};
+use crate::matcher::Matcher;
+use crate::stream::BUFFER_SIZE;
+use crate::walk::Input;
+use crate::{Match, STDIN_PATH};
+
+// a line changed by --in-place
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    // 1-based line number
+    pub line_no: usize,
+    // the line before and after, without its line ending
+    pub old: String,
+    pub new: String,
+}
+
+// --replace: a selected line as it is printed, with its matches replaced
+// (context lines are printed as they are)
+pub fn replace_match<'a>(matcher: &Matcher, replacement: &str, m: Match<'a>) -> Match<'a> {
+    if m.context || m.ranges.is_empty() {
+        return m;
+    }
+    let (line, ranges) = matcher.replace(&m.line, &m.ranges, replacement);
+    Match { line: line.into(), ranges, ..m }
+}
+
+// --in-place: replaces every match in the file at "path" and returns the lines that changed.
+// The new contents are written to a temporary file next to the original, which is then
+// renamed over it, so the file is never left half written.
+// Nothing is written with --dry-run, or if no line changed.
+// Files that look binary are left alone unless -a is given: silently if they were found
+// by walking a directory, like when searching, and as an error if they were named.
+// Other files have to be valid UTF-8.
+pub fn replace_in_file(config: &Config, matcher: &Matcher, replacement: &str, input: &Input) -> io::Result<Vec<Change>> {
+    let path = input.path.as_path();
+    if path.as_os_str() == STDIN_PATH {
+        return Err(io::Error::other("can't edit standard input in place"));
+    }
+    let bytes = fs::read(path)?;
+    let head = &bytes[..bytes.len().min(BUFFER_SIZE)];
+    if config.binary_files != BinaryFiles::Text && head.contains(&0) {
+        if input.walked {
+            return Ok(Vec::new());
+        }
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "binary file, not edited without -a"));
+    }
+    let contents = String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    let mut changes = Vec::new();
+    let mut new_contents = String::with_capacity(contents.len());
+    // split_inclusive keeps the line endings, so they are written back unchanged
+    for (i, line) in contents.split_inclusive('\n').enumerate() {
+        let text = line.strip_suffix('\n').map_or(line, |l| l.strip_suffix('\r').unwrap_or(l));
+        let ranges = matcher.find_ranges(text);
+        if ranges.is_empty() {
+            new_contents.push_str(line);
+            continue;
+        }
+        let (new, _) = matcher.replace(text, &ranges, replacement);
+        new_contents.push_str(&new);
+        new_contents.push_str(&line[text.len()..]);
+        if new != text {
+            changes.push(Change { line_no: i + 1, old: text.to_string(), new });
+        }
+    }
+
+    if !config.dry_run && !changes.is_empty() {
+        // a symlink is followed, so the file it points to is edited instead of being replaced
+        let path = fs::canonicalize(path)?;
+        let path = path.as_path();
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let mut temp = NamedTempFile::new_in(dir)?;
+        temp.write_all(new_contents.as_bytes())?;
+        temp.as_file().set_permissions(fs::metadata(path)?.permissions())?;
+        temp.persist(path).map_err(|err| err.error)?;
+    }
+    Ok(changes)
+}
@@ -0,0 +1,163 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+use memchr::{memchr_iter, memrchr, memmem};
+
+use crate::matcher::Matcher;
+use crate::Match;
+
+// size of the read buffer used for files and stdin
+// memory use while searching is this buffer plus the longest line, however big the input is
+pub const BUFFER_SIZE: usize = 64 * 1024;
+
+// Iterator over the matching lines of a BufRead, read one line at a time.
+// Unlike search(), which borrows slices out of a String holding the whole input,
+// every match is yielded with an owned copy of its line as soon as it is found.
+// With context(before, after) the lines around each match are yielded too, marked as context;
+// overlapping context is merged, so every line is yielded at most once.
+pub struct Matches<'m, R> {
+    reader: R,
+    matcher: &'m Matcher,
+    // the needle of a literal matcher (see Matcher::literal), to skip lines without it
+    finder: Option<memmem::Finder<'m>>,
+    invert: bool,
+    // reused for every line, so it only ever grows to the longest line seen
+    line: Vec<u8>,
+    line_no: usize,
+    // byte offset of the start of the next line
+    byte_offset: usize,
+    before: usize,
+    after: usize,
+    // the last "before" lines that weren't yielded, in case the next line matches
+    history: VecDeque<Match<'static>>,
+    // how many more lines to yield as context after the last match
+    after_left: usize,
+    // lines ready to be yielded
+    queue: VecDeque<Match<'static>>,
+}
+
+impl<'m, R: BufRead> Matches<'m, R> {
+    pub fn new(reader: R, matcher: &'m Matcher) -> Matches<'m, R> {
+        // U+FFFD can come from replacing invalid UTF-8, so it can't be looked for in the raw bytes
+        let finder = matcher.literal().filter(|needle| !needle.contains('\u{FFFD}'));
+        Matches {
+            reader,
+            matcher,
+            finder: finder.map(|needle| memmem::Finder::new(needle.as_bytes())),
+            invert: false,
+            line: Vec::new(),
+            line_no: 0,
+            byte_offset: 0,
+            before: 0,
+            after: 0,
+            history: VecDeque::new(),
+            after_left: 0,
+            queue: VecDeque::new(),
+        }
+    }
+
+    // yield the lines that don't match instead (-v)
+    pub fn invert(mut self, invert: bool) -> Matches<'m, R> {
+        self.invert = invert;
+        self
+    }
+
+    // also yield up to "before" lines before and "after" lines after each match (-B, -A)
+    pub fn context(mut self, before: usize, after: usize) -> Matches<'m, R> {
+        self.before = before;
+        self.after = after;
+        self
+    }
+
+    // Literal fast path, like LiteralLines (see literal.rs) but over the read buffer:
+    // the whole lines in the buffer are searched for the needle at once, and the ones
+    // before the first hit are skipped without being read one by one.
+    // Only possible when nothing but matching lines is yielded.
+    fn skip_to_hit(&mut self) -> io::Result<()> {
+        let Some(finder) = &self.finder else { return Ok(()) };
+        if self.invert || self.before > 0 || self.after > 0 {
+            return Ok(());
+        }
+        loop {
+            let buf = self.reader.fill_buf()?;
+            // a line that doesn't end in the buffer is left to read_until
+            let Some(last) = memrchr(b'\n', buf) else { return Ok(()) };
+            let lines = &buf[..=last];
+            let (skip, hit) = match finder.find(lines) {
+                Some(hit) => (memrchr(b'\n', &lines[..hit]).map_or(0, |i| i + 1), true),
+                None => (lines.len(), false),
+            };
+            self.line_no += memchr_iter(b'\n', &lines[..skip]).count();
+            self.byte_offset += skip;
+            self.reader.consume(skip);
+            if hit {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Matches<'_, R> {
+    type Item = io::Result<Match<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(m) = self.queue.pop_front() {
+            return Some(Ok(m));
+        }
+        loop {
+            if let Err(err) = self.skip_to_hit() {
+                return Some(Err(err));
+            }
+            self.line.clear();
+            // lines are read as bytes, since binary files (-a) needn't be valid UTF-8
+            let line_len = match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => return None,
+                Ok(len) => len,
+                Err(err) => return Some(Err(err)),
+            };
+            self.line_no += 1;
+            let byte_offset = self.byte_offset;
+            self.byte_offset += line_len;
+
+            // same line endings as str::lines(): "\n" or "\r\n"
+            let mut line = self.line.as_slice();
+            if let Some(rest) = line.strip_suffix(b"\n") {
+                line = rest.strip_suffix(b"\r").unwrap_or(rest);
+            }
+            // invalid UTF-8 is replaced with U+FFFD
+            let line = String::from_utf8_lossy(line);
+            let line = line.as_ref();
+
+            // is_match is cheaper than find_ranges, so ranges are only looked for in matching lines
+            let is_match = self.matcher.is_match(line);
+            let selected = is_match != self.invert;
+            if !selected && self.after_left == 0 && self.before == 0 {
+                continue;
+            }
+            let m = Match {
+                line_no: self.line_no,
+                byte_offset,
+                line: Cow::Owned(line.to_string()),
+                ranges: if is_match { self.matcher.find_ranges(line) } else { Vec::new() },
+                context: !selected,
+            };
+
+            if selected {
+                // the lines before this one come out first
+                self.queue.extend(self.history.drain(..));
+                self.queue.push_back(m);
+                self.after_left = self.after;
+                return self.queue.pop_front().map(Ok);
+            }
+            if self.after_left > 0 {
+                self.after_left -= 1;
+                return Some(Ok(m));
+            }
+            self.history.push_back(m);
+            if self.history.len() > self.before {
+                self.history.pop_front();
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+
+use crate::config::Config;
+
+// What to search when walking directories (-r).
+#[derive(Debug, Default, Clone)]
+pub struct WalkOptions {
+    // walk directories given as FILE arguments
+    pub recursive: bool,
+    // also search hidden files and directories (--hidden)
+    pub hidden: bool,
+    // don't skip files ignored by .gitignore, .ignore and friends (--no-ignore)
+    pub no_ignore: bool,
+    // -g GLOB: only search matching files, -g !GLOB: skip matching files
+    pub globs: Vec<String>,
+}
+
+impl WalkOptions {
+    pub fn from_config(config: &Config) -> WalkOptions {
+        WalkOptions {
+            recursive: config.recursive,
+            hidden: config.hidden,
+            no_ignore: config.no_ignore,
+            globs: config.globs.clone(),
+        }
+    }
+}
+
+// A file to search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Input {
+    pub path: PathBuf,
+    // found by walking a directory rather than named on the command line,
+    // so it is skipped if it turns out to be binary (unless -a), see lib.rs
+    pub walked: bool,
+}
+
+// Expands the FILE arguments into the list of files to search.
+// Files named on the command line are always searched. Directories are only walked with -r,
+// depth first with entries sorted by name, so output order is the same on every run.
+// While walking, hidden files, files ignored by .gitignore/.ignore and files left out by -g
+// are skipped, unless WalkOptions says otherwise. Binary files are only found out when
+// they are searched, so the walk doesn't have to open every file.
+// A file matching a -g GLOB is searched even if it is hidden or ignored, like in ripgrep.
+// Paths that can't be read are passed to "on_error" and skipped; the walk carries on.
+pub fn walk<F>(paths: &[String], options: &WalkOptions, mut on_error: F) -> Vec<Input>
+where
+    F: FnMut(&Path, io::Error),
+{
+    let mut files = Vec::new();
+    for path in paths {
+        let path = PathBuf::from(path);
+        if path.is_dir() {
+            if options.recursive {
+                walk_dir(&path, options, &mut files, &mut on_error);
+            } else {
+                on_error(&path, io::Error::other("Is a directory"));
+            }
+        } else {
+            // missing files are reported when they are opened
+            files.push(Input { path, walked: false });
+        }
+    }
+    files
+}
+
+fn walk_dir<F>(dir: &Path, options: &WalkOptions, files: &mut Vec<Input>, on_error: &mut F)
+where
+    F: FnMut(&Path, io::Error),
+{
+    // globs are matched relative to the directory being walked, like in a .gitignore there
+    let mut overrides = OverrideBuilder::new(dir);
+    for glob in &options.globs {
+        if let Err(err) = overrides.add(glob) {
+            return on_error(dir, io::Error::new(io::ErrorKind::InvalidInput, err));
+        }
+    }
+    let overrides = match overrides.build() {
+        Ok(overrides) => overrides,
+        Err(err) => return on_error(dir, io::Error::new(io::ErrorKind::InvalidInput, err)),
+    };
+
+    let walker = WalkBuilder::new(dir)
+        .hidden(!options.hidden)
+        .git_ignore(!options.no_ignore)
+        .git_global(!options.no_ignore)
+        .git_exclude(!options.no_ignore)
+        .ignore(!options.no_ignore)
+        .parents(!options.no_ignore)
+        // .gitignore files count even outside a git repository
+        .require_git(false)
+        .overrides(overrides)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                on_error(dir, io::Error::other(err));
+                continue;
+            }
+        };
+        // symlinks found while walking aren't followed, like grep -r
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        files.push(Input { path: entry.into_path(), walked: true });
+    }
+}
@@ -0,0 +1,465 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// the minigrep binary with "args", in the crate directory and without the user's environment
+fn command(args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_minigrep"));
+    command
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env_remove("CASE_INSENSITIVE")
+        // keep the user's own defaults out of the tests
+        .env_remove("MINIGREP_OPTS")
+        .env_remove("MINIGREP_CONFIG")
+        .env("XDG_CONFIG_HOME", "no_such_dir");
+    command
+}
+
+// runs the minigrep binary with "args", feeding "stdin" to it
+fn minigrep(args: &[&str], stdin: &str) -> Output {
+    let mut child = command(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start minigrep");
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn reads_stdin_without_file() {
+    let output = minigrep(&["-n", "three"], "Rust:\nsafe, fast, productive.\nPick three.\n");
+    assert!(output.status.success());
+    assert_eq!("3:Pick three.\n", stdout(&output));
+}
+
+#[test]
+fn dash_means_stdin() {
+    let output = minigrep(&["-c", "frog", "-", "poem.txt"], "frog\nfrog\n");
+    assert_eq!("(standard input):2\npoem.txt:1\n", stdout(&output));
+}
+
+#[test]
+fn line_number_column_and_byte_offset() {
+    let output = minigrep(&["-b", "--column", "foo"], "a foo\nbar\nfoo b foo\n");
+    assert_eq!("1:3:0:a foo\n3:1:10:foo b foo\n", stdout(&output));
+
+    let output = minigrep(&["-nb", "bar"], "a foo\nbar\n");
+    assert_eq!("2:6:bar\n", stdout(&output));
+}
+
+#[test]
+fn context_with_separators() {
+    let input = "1\n2 x\n3\n4\n5\n6\n7 x\n8\n9 x\n10\n";
+    let output = minigrep(&["-n", "-C1", "x"], input);
+    assert_eq!("1-1\n2:2 x\n3-3\n--\n6-6\n7:7 x\n8-8\n9:9 x\n10-10\n", stdout(&output));
+
+    let output = minigrep(&["-A", "1", "-B0", "x"], input);
+    assert_eq!("2 x\n3\n--\n7 x\n8\n9 x\n10\n", stdout(&output));
+}
+
+#[test]
+fn output_modes() {
+    let output = minigrep(&["-v", "-c", "frog", "poem.txt"], "");
+    assert_eq!("8\n", stdout(&output));
+
+    let output = minigrep(&["-l", "frog", "-", "poem.txt", "Cargo.toml"], "no\n");
+    assert_eq!("poem.txt\n", stdout(&output));
+
+    let output = minigrep(&["-L", "frog", "-", "poem.txt", "Cargo.toml"], "no\n");
+    assert_eq!("(standard input)\nCargo.toml\n", stdout(&output));
+
+    let output = minigrep(&["-q", "frog", "poem.txt"], "");
+    assert!(output.status.success());
+    assert_eq!("", stdout(&output));
+}
+
+#[test]
+fn exit_codes() {
+    assert_eq!(Some(0), minigrep(&["frog", "poem.txt"], "").status.code());
+    assert_eq!(Some(1), minigrep(&["toad", "poem.txt"], "").status.code());
+    assert_eq!(Some(2), minigrep(&["frog", "poem.txt", "missing.txt"], "").status.code());
+    assert_eq!(Some(2), minigrep(&["-E", "(frog", "poem.txt"], "").status.code());
+    assert_eq!(Some(2), minigrep(&["--frog"], "").status.code());
+    assert_eq!(Some(0), minigrep(&["--help"], "").status.code());
+
+    // -q succeeds on a match even if another file couldn't be read
+    assert_eq!(Some(0), minigrep(&["-q", "frog", "missing.txt", "poem.txt"], "").status.code());
+    assert_eq!(Some(2), minigrep(&["-q", "toad", "missing.txt", "poem.txt"], "").status.code());
+}
+
+#[test]
+fn color_always() {
+    let output = minigrep(&["--color=always", "-n", "o", "-"], "a foo\nbar\n");
+    assert_eq!(
+        "\x1b[32m1\x1b[0m\x1b[36m:\x1b[0ma f\x1b[1;31mo\x1b[0m\x1b[1;31mo\x1b[0m\n",
+        stdout(&output)
+    );
+
+    let output = minigrep(&["--color=always", "-l", "frog", "poem.txt"], "");
+    assert_eq!("\x1b[35mpoem.txt\x1b[0m\n", stdout(&output));
+}
+
+#[test]
+fn color_auto_and_never() {
+    // stdout is a pipe here, so auto doesn't color
+    let output = minigrep(&["--color", "foo"], "a foo\n");
+    assert_eq!("a foo\n", stdout(&output));
+
+    let output = minigrep(&["--color=never", "foo"], "a foo\n");
+    assert_eq!("a foo\n", stdout(&output));
+}
+
+#[test]
+fn word_and_line_regexp() {
+    let input = "safe, fast, productive.\nDuct tape.\nduct\n";
+    assert_eq!("duct\n", stdout(&minigrep(&["-w", "duct"], input)));
+    assert_eq!("Duct tape.\nduct\n", stdout(&minigrep(&["-wi", "duct"], input)));
+    assert_eq!("duct\n", stdout(&minigrep(&["-xi", "duct"], input)));
+    // the regex is anchored, so the longer alternative can match the whole line
+    assert_eq!("Duct tape.\n", stdout(&minigrep(&["-xE", r"Duct|Duct tape\."], input)));
+    // and -w looks for an alternative that is a whole word
+    assert_eq!("foobar\n", stdout(&minigrep(&["-wE", "foo|foobar"], "foobar\n")));
+    assert_eq!("xa a a\n", stdout(&minigrep(&["-w", "a a"], "xa a a\n")));
+    assert_eq!("foobar\nfoo\n", stdout(&minigrep(&["-woE", "foo|foobar"], "foobar foo foos\n")));
+}
+
+// a directory tree with enough files to keep several threads busy
+fn make_tree(name: &str) -> std::path::PathBuf {
+    let root = std::env::temp_dir().join(format!("minigrep_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    for dir in 0..4 {
+        let dir_path = root.join(format!("dir{}", dir));
+        std::fs::create_dir_all(&dir_path).unwrap();
+        for file in 0..25 {
+            let mut contents = String::new();
+            for line in 0..200 {
+                if line % 50 == file % 50 {
+                    contents.push_str(&format!("needle in dir{} file{} line{}\n", dir, file, line));
+                } else {
+                    contents.push_str("hay\n");
+                }
+            }
+            std::fs::write(dir_path.join(format!("file{:02}.txt", file)), contents).unwrap();
+        }
+    }
+    root
+}
+
+#[test]
+fn parallel_output_is_deterministic() {
+    let root = make_tree("parallel");
+    let root_arg = root.to_str().unwrap();
+
+    let sequential = minigrep(&["-r", "-j1", "-n", "-C1", "needle", root_arg], "");
+    assert!(sequential.status.success());
+    for threads in ["-j2", "-j8"] {
+        let parallel = minigrep(&["-r", threads, "-n", "-C1", "needle", root_arg], "");
+        assert_eq!(stdout(&sequential), stdout(&parallel));
+    }
+
+    // every file appears once, in sorted order, and its lines aren't mixed with other files
+    let counts = stdout(&minigrep(&["-r", "-j8", "-c", "needle", root_arg], ""));
+    let names: Vec<&str> = counts.lines().map(|line| line.rsplit_once(':').unwrap().0).collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(100, names.len());
+    assert_eq!(sorted, names);
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn recursive_skips_ignored_hidden_and_binary_files() {
+    let root = std::env::temp_dir().join(format!("minigrep_ignore_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+    std::fs::write(root.join("a.txt"), "frog\n").unwrap();
+    std::fs::write(root.join("debug.log"), "frog\n").unwrap();
+    std::fs::write(root.join(".hidden.txt"), "frog\n").unwrap();
+    std::fs::write(root.join("data.bin"), b"frog\0\x01\x02\n").unwrap();
+    std::fs::write(root.join("sub/x.txt"), "frog\n").unwrap();
+    std::fs::write(root.join("sub/y.md"), "frog\n").unwrap();
+    let root_arg = root.to_str().unwrap();
+    let names = |output: &Output| -> Vec<String> {
+        stdout(output).lines().map(|line| line.strip_prefix(root_arg).unwrap().to_string()).collect()
+    };
+
+    let output = minigrep(&["-rl", "frog", root_arg], "");
+    assert_eq!(vec!["/a.txt", "/sub/x.txt", "/sub/y.md"], names(&output));
+
+    let output = minigrep(&["-rl", "--hidden", "--no-ignore", "frog", root_arg], "");
+    assert_eq!(vec!["/.hidden.txt", "/a.txt", "/debug.log", "/sub/x.txt", "/sub/y.md"], names(&output));
+
+    let output = minigrep(&["-rl", "-g", "*.md", "frog", root_arg], "");
+    assert_eq!(vec!["/sub/y.md"], names(&output));
+    // like in ripgrep, files matching a -g glob are searched even when hidden or ignored
+    let output = minigrep(&["-rl", "-g", "*.txt", "-g", "!x*", "frog", root_arg], "");
+    assert_eq!(vec!["/.hidden.txt", "/a.txt"], names(&output));
+
+    // binary files are found out when they are searched, with the same check as for
+    // other inputs, so a NUL byte further in still counts; the workers skip them too
+    let mut late = "frog\n".repeat(4000).into_bytes();
+    late.push(0);
+    std::fs::write(root.join("sub/late.bin"), late).unwrap();
+    let output = minigrep(&["-rl", "-j2", "frog", root_arg], "");
+    assert_eq!(vec!["/a.txt", "/sub/x.txt", "/sub/y.md"], names(&output));
+    let output = minigrep(&["-r", "--json", "frog", root_arg], "");
+    assert!(!stdout(&output).contains(".bin"));
+    let output = minigrep(&["-rla", "frog", root_arg], "");
+    assert_eq!(vec!["/a.txt", "/data.bin", "/sub/late.bin", "/sub/x.txt", "/sub/y.md"], names(&output));
+
+    // files named on the command line are always searched
+    let log = root.join("debug.log");
+    let output = minigrep(&["-c", "frog", log.to_str().unwrap()], "");
+    assert_eq!("1\n", stdout(&output));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn binary_files() {
+    let input = "frog\0\nbog frog\n";
+    let output = minigrep(&["frog"], input);
+    assert!(output.status.success());
+    assert_eq!("Binary file (standard input) matches\n", stdout(&output));
+
+    let output = minigrep(&["toad"], input);
+    assert_eq!(Some(1), output.status.code());
+    assert_eq!("", stdout(&output));
+
+    // -c still counts the lines
+    let output = minigrep(&["-c", "frog"], input);
+    assert_eq!("2\n", stdout(&output));
+
+    let output = minigrep(&["-a", "-n", "bog"], input);
+    assert_eq!("2:bog frog\n", stdout(&output));
+
+    let output = minigrep(&["--binary-files=without-match", "frog"], input);
+    assert_eq!(Some(1), output.status.code());
+    assert_eq!("", stdout(&output));
+}
+
+#[test]
+fn encodings() {
+    let dir = std::env::temp_dir().join(format!("minigrep_encodings_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let latin1 = dir.join("latin1.txt");
+    std::fs::write(&latin1, b"caf\xE9 frog\nbog\n").unwrap();
+    let utf16 = dir.join("utf16.txt");
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend("bog\ncafé frog\n".encode_utf16().flat_map(u16::to_le_bytes));
+    std::fs::write(&utf16, bytes).unwrap();
+
+    // invalid UTF-8 doesn't stop the search, it is printed as U+FFFD
+    let output = minigrep(&["frog", latin1.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!("caf\u{FFFD} frog\n", stdout(&output));
+
+    let output = minigrep(&["--encoding=latin-1", "café", latin1.to_str().unwrap()], "");
+    assert_eq!("café frog\n", stdout(&output));
+
+    // UTF-16 with a byte order mark is found without --encoding, also by -r
+    let output = minigrep(&["-n", "café", utf16.to_str().unwrap()], "");
+    assert_eq!("2:café frog\n", stdout(&output));
+    let output = minigrep(&["-rl", "café", dir.to_str().unwrap()], "");
+    assert_eq!(format!("{}\n", utf16.display()), stdout(&output));
+
+    // without a byte order mark, -r needs --encoding to know it isn't binary
+    let utf16be = dir.join("utf16be.txt");
+    std::fs::write(&utf16be, "bog\ncafé frog\n".encode_utf16().flat_map(u16::to_be_bytes).collect::<Vec<u8>>()).unwrap();
+    let output = minigrep(&["-rl", "--encoding=utf-16be", "frog", dir.to_str().unwrap()], "");
+    assert!(stdout(&output).contains("utf16be.txt\n"));
+
+    let output = minigrep(&["--encoding=ebcdic", "frog"], "");
+    assert_eq!(Some(2), output.status.code());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn json_output() {
+    let output = minigrep(&["--json", "-A1", "frog"], "bog\na frog\ncafé\n");
+    assert!(output.status.success());
+    let records: Vec<serde_json::Value> = stdout(&output).lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let types: Vec<&str> = records.iter().map(|r| r["type"].as_str().unwrap()).collect();
+    assert_eq!(vec!["begin", "match", "context", "end", "summary"], types);
+
+    let data = &records[1]["data"];
+    assert_eq!("(standard input)", data["path"]);
+    assert_eq!(2, data["line_number"]);
+    assert_eq!(4, data["byte_offset"]);
+    assert_eq!("a frog", data["line"]);
+    assert_eq!(serde_json::json!([{"match": "frog", "start": 2, "end": 6}]), data["submatches"]);
+    assert_eq!("café", records[2]["data"]["line"]);
+    assert_eq!(1, records[3]["data"]["matches"]);
+    assert_eq!(serde_json::json!({"files": 1, "files_with_matches": 1, "matches": 1}), records[4]["data"]);
+
+    // every file gets a begin and end record, even without matches
+    let output = minigrep(&["--json", "-j2", "toad", "poem.txt", "-"], "");
+    assert_eq!(Some(1), output.status.code());
+    assert_eq!(5, stdout(&output).lines().count());
+}
+
+#[test]
+fn replace() {
+    let output = minigrep(&["-n", "--replace=toad", "frog"], "a frog\nbog\nfrog frog\n");
+    assert_eq!("1:a toad\n3:toad toad\n", stdout(&output));
+
+    let output = minigrep(&["-E", "--replace", "<$1>", r"f(r)og"], "a frog\n");
+    assert_eq!("a <r>\n", stdout(&output));
+}
+
+#[test]
+fn replace_in_place() {
+    let dir = std::env::temp_dir().join(format!("minigrep_in_place_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("poem.txt");
+    let contents = "a frog\r\nbog\nfrog and frog";
+    std::fs::write(&path, contents).unwrap();
+    let path_arg = path.to_str().unwrap();
+
+    let output = minigrep(&["--replace=toad", "--in-place", "--dry-run", "frog", path_arg], "");
+    assert!(output.status.success());
+    let diff = format!("--- {0}\n+++ {0}\n@@ -1 +1 @@\n-a frog\n+a toad\n@@ -3 +3 @@\n-frog and frog\n+toad and toad\n", path_arg);
+    assert_eq!(diff, stdout(&output));
+    assert_eq!(contents, std::fs::read_to_string(&path).unwrap());
+
+    let output = minigrep(&["--replace=toad", "--in-place", "frog", path_arg], "");
+    assert!(output.status.success());
+    assert_eq!("", stdout(&output));
+    // line endings are kept
+    assert_eq!("a toad\r\nbog\ntoad and toad", std::fs::read_to_string(&path).unwrap());
+
+    let output = minigrep(&["--replace=toad", "--in-place", "frog", path_arg], "");
+    assert_eq!(Some(1), output.status.code());
+
+    // binary files found by walking are skipped, like when searching, even if they aren't UTF-8
+    std::fs::write(dir.join("img.png"), b"\x89PNG\r\n\x1a\n\0\0\xff frog").unwrap();
+    let output = minigrep(&["-r", "--replace=frog", "--in-place", "toad", dir.to_str().unwrap()], "");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!("a frog\r\nbog\nfrog and frog", std::fs::read_to_string(&path).unwrap());
+    std::fs::write(&path, "a toad\r\nbog\ntoad and toad").unwrap();
+    // but not when they are named
+    let output = minigrep(&["--replace=toad", "--in-place", "frog", dir.join("img.png").to_str().unwrap()], "");
+    assert_eq!(Some(2), output.status.code());
+    std::fs::remove_file(dir.join("img.png")).unwrap();
+
+    // a symlink is followed: the file it points to is edited and the link stays a link
+    #[cfg(unix)]
+    {
+        let link = dir.join("link.txt");
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+        let output = minigrep(&["--replace=frog", "--in-place", "toad", link.to_str().unwrap()], "");
+        assert!(output.status.success());
+        assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(contents, std::fs::read_to_string(&path).unwrap());
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn only_matching() {
+    let output = minigrep(&["-o", "-n", "-b", "frog"], "a frog and a frog\nbog\nfrog\n");
+    assert_eq!("1:2:frog\n1:13:frog\n3:22:frog\n", stdout(&output));
+
+    let output = minigrep(&["-o", "--column", "-E", "-i", "[bf]r?og"], "BOG, frog, fog\n");
+    assert_eq!("1:1:BOG\n1:6:frog\n1:12:fog\n", stdout(&output));
+
+    // context lines and lines selected by -v have nothing to print
+    let output = minigrep(&["-o", "-C1", "frog"], "bog\nfrog\nbog\n");
+    assert_eq!("frog\n", stdout(&output));
+    let output = minigrep(&["-o", "-v", "frog"], "bog\nfrog\n");
+    assert_eq!("", stdout(&output));
+}
+
+#[test]
+fn search_zip() {
+    let dir = std::env::temp_dir().join(format!("minigrep_zip_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let text = "bog\na frog\n".repeat(1000);
+
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(text.as_bytes()).unwrap();
+    let gz = gz.finish().unwrap();
+    // two gzip streams one after the other, like concatenated log files
+    std::fs::write(dir.join("a.log.gz"), [&gz[..], &gz[..]].concat()).unwrap();
+    let mut bz2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+    bz2.write_all(text.as_bytes()).unwrap();
+    std::fs::write(dir.join("b.log.bz2"), bz2.finish().unwrap()).unwrap();
+    let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+    xz.write_all(text.as_bytes()).unwrap();
+    // no extension: found by its magic bytes
+    std::fs::write(dir.join("c.log"), xz.finish().unwrap()).unwrap();
+    std::fs::write(dir.join("d.log.zst"), zstd::encode_all(text.as_bytes(), 0).unwrap()).unwrap();
+
+    let output = minigrep(&["-rcz", "frog", dir.to_str().unwrap()], "");
+    assert!(output.status.success());
+    let counts = stdout(&output);
+    let counts: Vec<&str> = counts.lines().map(|line| line.rsplit(':').next().unwrap()).collect();
+    assert_eq!(vec!["2000", "1000", "1000", "1000"], counts);
+
+    let gz_path = dir.join("a.log.gz");
+    let output = minigrep(&["-zn", "frog", gz_path.to_str().unwrap()], "");
+    assert_eq!("2:a frog", stdout(&output).lines().next().unwrap());
+
+    // without -z compressed files are searched as they are
+    let output = minigrep(&["-c", "frog", gz_path.to_str().unwrap()], "");
+    assert_eq!("0\n", stdout(&output));
+
+    // text that happens to start like a bzip2 file isn't decompressed
+    let output = minigrep(&["-z", "frog"], "BZh9 frog\n");
+    assert_eq!("BZh9 frog\n", stdout(&output));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mapped_files_match_buffered_search() {
+    let path = std::env::temp_dir().join(format!("minigrep_mapped_{}.txt", std::process::id()));
+    let mut contents = String::new();
+    for i in 0..50_000 {
+        contents.push_str(if i % 997 == 0 { "a Frog\r\n" } else { "just bog\n" });
+    }
+    std::fs::write(&path, &contents).unwrap();
+    let path_arg = path.to_str().unwrap();
+
+    // a single big file is mapped, standard input never is
+    for args in [&["-nb", "Frog"][..], &["-ni", "frog"], &["-n", "-C1", "Frog"], &["-cv", "Frog"], &["-o", "-E", "F.o"]] {
+        let mapped = minigrep(&[args, &[path_arg]].concat(), "");
+        let buffered = minigrep(args, &contents);
+        assert!(mapped.status.success());
+        assert_eq!(stdout(&buffered), stdout(&mapped), "{:?}", args);
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn closed_pipe_still_reports_a_match() {
+    use std::io::{BufRead, BufReader};
+
+    let path = std::env::temp_dir().join(format!("minigrep_pipe_{}.txt", std::process::id()));
+    std::fs::write(&path, "a frog\n".repeat(200_000)).unwrap();
+    for threads in ["-j1", "-j2"] {
+        let mut child = command(&[threads, "frog", path.to_str().unwrap(), path.to_str().unwrap()])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // like "| head -1"
+        let mut first = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut first).unwrap();
+        assert!(first.ends_with(":a frog\n"));
+        assert_eq!(Some(0), child.wait().unwrap().code());
+    }
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,346 @@
+use minigrep::config::{ArgsError, BinaryFiles, ColorChoice, Config, OutputMode};
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+// parse with an empty environment
+fn parse(list: &[&str]) -> Result<Config, ArgsError> {
+    Config::parse_args_env(args(list), |_| None)
+}
+
+#[test]
+fn positional_only() {
+    let config = parse(&["minigrep", "frog", "poem.txt"]).unwrap();
+    assert_eq!("frog", config.patterns[0]);
+    assert_eq!(vec!["poem.txt"], config.filenames);
+    assert!(config.case_sensitive);
+    assert!(!config.invert_match);
+}
+
+#[test]
+fn combined_short_flags() {
+    let config = parse(&["minigrep", "-vnc", "frog", "poem.txt"]).unwrap();
+    assert!(config.invert_match);
+    assert!(config.line_number);
+    assert_eq!(OutputMode::Count, config.output);
+}
+
+#[test]
+fn long_flags_after_positionals() {
+    let config = parse(&["minigrep", "frog", "poem.txt", "--ignore-case", "--recursive"]).unwrap();
+    assert!(!config.case_sensitive);
+    assert!(config.recursive);
+}
+
+#[test]
+fn double_dash_ends_options() {
+    let config = parse(&["minigrep", "-l", "--", "-v", "poem.txt"]).unwrap();
+    assert_eq!("-v", config.patterns[0]);
+    assert_eq!(OutputMode::FilesWithMatches, config.output);
+    assert!(!config.invert_match);
+}
+
+#[test]
+fn help_and_version() {
+    assert_eq!(Some(ArgsError::Help), parse(&["minigrep", "-h"]).err());
+    assert_eq!(Some(ArgsError::Help), parse(&["minigrep", "frog", "--help"]).err());
+    assert_eq!(Some(ArgsError::Version), parse(&["minigrep", "--version"]).err());
+}
+
+#[test]
+fn usage_errors() {
+    assert_eq!(
+        Some(ArgsError::Usage(String::from("unknown option '-y'"))),
+        parse(&["minigrep", "-iy", "frog", "poem.txt"]).err()
+    );
+    assert_eq!(
+        Some(ArgsError::Usage(String::from("unknown option '--frog'"))),
+        parse(&["minigrep", "--frog", "poem.txt"]).err()
+    );
+    assert_eq!(
+        Some(ArgsError::Usage(String::from("unknown option '--foo'"))),
+        parse(&["minigrep", "--foo=bar", "frog"]).err()
+    );
+    assert_eq!(
+        Some(ArgsError::Usage(String::from("option '--count' doesn't allow an argument"))),
+        parse(&["minigrep", "--count=3", "frog"]).err()
+    );
+    assert!(matches!(parse(&["minigrep"]), Err(ArgsError::Usage(_))));
+}
+
+#[test]
+fn flags_override_env() {
+    let env = |key: &str| if key == "CASE_INSENSITIVE" { Some(String::from("1")) } else { None };
+
+    let config = Config::parse_args_env(args(&["minigrep", "frog", "poem.txt"]), env).unwrap();
+    assert!(!config.case_sensitive);
+
+    let config = Config::parse_args_env(args(&["minigrep", "-s", "frog", "poem.txt"]), env).unwrap();
+    assert!(config.case_sensitive);
+
+    // last flag wins
+    let config = Config::parse_args_env(args(&["minigrep", "-s", "-i", "frog", "poem.txt"]), |_| None).unwrap();
+    assert!(!config.case_sensitive);
+}
+
+#[test]
+fn multiple_files() {
+    let config = parse(&["minigrep", "-r", "frog", "poem.txt", "src"]).unwrap();
+    assert_eq!(vec!["poem.txt", "src"], config.filenames);
+    assert!(config.recursive);
+}
+
+#[test]
+fn stdin_when_no_file() {
+    let config = parse(&["minigrep", "frog"]).unwrap();
+    assert_eq!(vec!["-"], config.filenames);
+
+    let config = parse(&["minigrep", "frog", "-", "poem.txt"]).unwrap();
+    assert_eq!(vec!["-", "poem.txt"], config.filenames);
+}
+
+#[test]
+fn context_options() {
+    let config = parse(&["minigrep", "-C", "3", "frog"]).unwrap();
+    assert_eq!((3, 3), (config.before_context, config.after_context));
+
+    // -A and -B win over -C in any order
+    let config = parse(&["minigrep", "-A1", "--context=4", "--before-context", "2", "frog"]).unwrap();
+    assert_eq!((2, 1), (config.before_context, config.after_context));
+
+    let config = parse(&["minigrep", "-nB5", "frog"]).unwrap();
+    assert!(config.line_number);
+    assert_eq!((5, 0), (config.before_context, config.after_context));
+
+    assert!(matches!(parse(&["minigrep", "-A", "many", "frog"]), Err(ArgsError::Usage(_))));
+    assert!(matches!(parse(&["minigrep", "frog", "-C"]), Err(ArgsError::Usage(_))));
+    assert!(matches!(parse(&["minigrep", "--count=2", "frog"]), Err(ArgsError::Usage(_))));
+}
+
+#[test]
+fn output_mode_precedence() {
+    assert_eq!(OutputMode::Lines, parse(&["minigrep", "frog"]).unwrap().output);
+    assert_eq!(OutputMode::FilesWithMatches, parse(&["minigrep", "-l", "-c", "frog"]).unwrap().output);
+    assert_eq!(OutputMode::FilesWithMatches, parse(&["minigrep", "-cl", "frog"]).unwrap().output);
+    assert_eq!(OutputMode::FilesWithoutMatch, parse(&["minigrep", "-cL", "frog"]).unwrap().output);
+    assert_eq!(OutputMode::Quiet, parse(&["minigrep", "-q", "-l", "frog"]).unwrap().output);
+    assert_eq!(OutputMode::Quiet, parse(&["minigrep", "--files-with-matches", "--quiet", "frog"]).unwrap().output);
+}
+
+#[test]
+fn color_option() {
+    assert_eq!(ColorChoice::Auto, parse(&["minigrep", "frog"]).unwrap().color);
+    assert_eq!(ColorChoice::Always, parse(&["minigrep", "--color=always", "frog"]).unwrap().color);
+
+    // a bare --color doesn't swallow the pattern
+    let config = parse(&["minigrep", "--color", "frog"]).unwrap();
+    assert_eq!(ColorChoice::Auto, config.color);
+    assert_eq!("frog", config.patterns[0]);
+
+    assert!(matches!(parse(&["minigrep", "--color=blue", "frog"]), Err(ArgsError::Usage(_))));
+}
+
+#[test]
+fn smart_case() {
+    let config = parse(&["minigrep", "--smart-case", "frog"]).unwrap();
+    assert!(config.smart_case);
+    assert!(!config.case_sensitive);
+
+    let config = parse(&["minigrep", "-S", "Frog"]).unwrap();
+    assert!(config.case_sensitive);
+
+    // escapes in a regex don't count as uppercase
+    let config = parse(&["minigrep", "-ES", r"\Sfrog\W"]).unwrap();
+    assert!(!config.case_sensitive);
+    let config = parse(&["minigrep", "-S", r"\Sfrog"]).unwrap();
+    assert!(config.case_sensitive);
+
+    // the last of -i, -s and -S wins
+    assert!(parse(&["minigrep", "-S", "-s", "frog"]).unwrap().case_sensitive);
+    assert!(!parse(&["minigrep", "-S", "-i", "Frog"]).unwrap().case_sensitive);
+    let config = parse(&["minigrep", "-i", "-S", "Frog"]).unwrap();
+    assert!(config.smart_case);
+    assert!(config.case_sensitive);
+}
+
+#[test]
+fn smart_case_overrides_env() {
+    let env = |key: &str| if key == "CASE_INSENSITIVE" { Some(String::from("1")) } else { None };
+    let config = Config::parse_args_env(args(&["minigrep", "-S", "Frog"]), env).unwrap();
+    assert!(config.case_sensitive);
+    let config = Config::parse_args_env(args(&["minigrep", "-S", "frog"]), env).unwrap();
+    assert!(!config.case_sensitive);
+}
+
+#[test]
+fn multiple_patterns() {
+    // with -e every positional argument is a file
+    let config = parse(&["minigrep", "-e", "frog", "--regexp=bog", "poem.txt"]).unwrap();
+    assert_eq!(vec!["frog", "bog"], config.patterns);
+    assert_eq!(vec!["poem.txt"], config.filenames);
+
+    let path = std::env::temp_dir().join(format!("minigrep_patterns_{}.txt", std::process::id()));
+    std::fs::write(&path, "nobody\r\nSomebody\n").unwrap();
+    let config = parse(&["minigrep", "-e", "frog", "-f", path.to_str().unwrap(), "-S"]).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(vec!["frog", "nobody", "Somebody"], config.patterns);
+    assert_eq!(vec!["-"], config.filenames);
+    // smart case looks at every pattern
+    assert!(config.case_sensitive);
+
+    assert!(matches!(parse(&["minigrep", "-f", "no_such_file.txt"]), Err(ArgsError::Usage(_))));
+}
+
+#[test]
+fn threads_option() {
+    assert!(parse(&["minigrep", "frog"]).unwrap().threads >= 1);
+    assert_eq!(4, parse(&["minigrep", "-rj4", "frog"]).unwrap().threads);
+    assert_eq!(2, parse(&["minigrep", "--threads=2", "frog"]).unwrap().threads);
+    assert!(matches!(parse(&["minigrep", "-j0", "frog"]), Err(ArgsError::Usage(_))));
+}
+
+#[test]
+fn walk_options() {
+    let config = parse(&["minigrep", "-r", "frog", "."]).unwrap();
+    assert!(!config.hidden && !config.no_ignore);
+    assert!(config.globs.is_empty());
+
+    let config = parse(&["minigrep", "-r", "--hidden", "--no-ignore", "-g", "*.rs", "--glob=!target/*", "frog"]).unwrap();
+    assert!(config.hidden && config.no_ignore);
+    assert_eq!(vec!["*.rs", "!target/*"], config.globs);
+}
+
+#[test]
+fn binary_files_option() {
+    assert_eq!(BinaryFiles::Binary, parse(&["minigrep", "frog"]).unwrap().binary_files);
+    assert_eq!(BinaryFiles::Text, parse(&["minigrep", "-a", "frog"]).unwrap().binary_files);
+    let config = parse(&["minigrep", "--binary-files", "without-match", "frog"]).unwrap();
+    assert_eq!(BinaryFiles::WithoutMatch, config.binary_files);
+    assert!(matches!(parse(&["minigrep", "--binary-files=skip", "frog"]), Err(ArgsError::Usage(_))));
+}
+
+#[test]
+fn json_option() {
+    assert!(parse(&["minigrep", "--json", "frog"]).unwrap().json);
+    assert!(matches!(parse(&["minigrep", "--json", "-c", "frog"]), Err(ArgsError::Usage(_))));
+}
+
+#[test]
+fn replace_options() {
+    let config = parse(&["minigrep", "--replace", "toad", "--in-place", "--dry-run", "frog", "poem.txt"]).unwrap();
+    assert_eq!(Some(String::from("toad")), config.replace);
+    assert!(config.in_place && config.dry_run);
+
+    assert!(matches!(parse(&["minigrep", "--in-place", "frog"]), Err(ArgsError::Usage(_))));
+    assert!(matches!(parse(&["minigrep", "--replace=x", "--dry-run", "frog"]), Err(ArgsError::Usage(_))));
+    assert!(matches!(parse(&["minigrep", "--replace=x", "--in-place", "-v", "frog"]), Err(ArgsError::Usage(_))));
+    for option in ["-c", "-l", "-L", "-q", "-o", "--json", "-z", "--encoding=latin-1"] {
+        let result = parse(&["minigrep", "--replace=x", "--in-place", option, "frog"]);
+        assert!(matches!(result, Err(ArgsError::Usage(_))), "{}", option);
+    }
+    assert!(parse(&["minigrep", "--replace=x", "--in-place", "--encoding=utf-8", "frog"]).is_ok());
+}
+
+#[test]
+fn config_file_and_minigrep_opts() {
+    let path = std::env::temp_dir().join(format!("minigrep_config_{}", std::process::id()));
+    std::fs::write(&path, "# defaults\n--smart-case\n\n  -n\n--glob\n!*.min.js\n").unwrap();
+    let path_arg = path.to_str().unwrap().to_string();
+    let env = |key: &str| match key {
+        "MINIGREP_CONFIG" => Some(path_arg.clone()),
+        "MINIGREP_OPTS" => Some(String::from("-C2 --color=never")),
+        _ => None,
+    };
+
+    let config = Config::parse_args_env(args(&["minigrep", "-A", "1", "frog", "poem.txt"]), env).unwrap();
+    assert!(config.smart_case && config.line_number);
+    assert_eq!(vec!["!*.min.js"], config.globs);
+    assert_eq!(ColorChoice::Never, config.color);
+    // the command line wins
+    assert_eq!((2, 1), (config.before_context, config.after_context));
+    assert_eq!(vec!["frog"], config.patterns);
+    assert_eq!(vec!["poem.txt"], config.filenames);
+
+    let config = Config::parse_args_env(args(&["minigrep", "-s", "Frog", "--no-config"]), env).unwrap();
+    assert!(!config.smart_case && !config.line_number && config.globs.is_empty());
+    assert_eq!(ColorChoice::Auto, config.color);
+
+    // defaults can't be anything but options
+    std::fs::write(&path, "--context\n").unwrap();
+    assert!(matches!(Config::parse_args_env(args(&["minigrep", "frog"]), env), Err(ArgsError::Usage(_))));
+    std::fs::write(&path, "frog\n").unwrap();
+    assert!(matches!(Config::parse_args_env(args(&["minigrep", "frog"]), env), Err(ArgsError::Usage(_))));
+    std::fs::remove_file(&path).unwrap();
+    // MINIGREP_CONFIG has to exist, the default config file doesn't
+    assert!(matches!(Config::parse_args_env(args(&["minigrep", "frog"]), env), Err(ArgsError::Usage(_))));
+    let home = |key: &str| (key == "HOME").then(|| String::from("/no/such/home"));
+    assert!(Config::parse_args_env(args(&["minigrep", "frog"]), home).is_ok());
+}
+
+#[test]
+fn command_line_replaces_defaults_of_the_same_kind() {
+    let with_opts = |opts: &'static str, command_line: &[&str]| {
+        Config::parse_args_env(args(command_line), move |key| (key == "MINIGREP_OPTS").then(|| opts.to_string()))
+    };
+
+    // context: -C replaces a default -A, -A only replaces the "after" half of a default -C
+    let config = with_opts("-A5", &["minigrep", "-C1", "frog"]).unwrap();
+    assert_eq!((1, 1), (config.before_context, config.after_context));
+    let config = with_opts("-C2", &["minigrep", "-A1", "frog"]).unwrap();
+    assert_eq!((2, 1), (config.before_context, config.after_context));
+
+    // output mode, including --json
+    assert_eq!(OutputMode::Count, with_opts("-l", &["minigrep", "-c", "frog"]).unwrap().output);
+    let config = with_opts("--json", &["minigrep", "-c", "frog"]).unwrap();
+    assert!(!config.json && config.output == OutputMode::Count);
+    assert_eq!(OutputMode::Quiet, with_opts("--json", &["minigrep", "-q", "frog"]).unwrap().output);
+    assert!(with_opts("-c", &["minigrep", "--json", "frog"]).unwrap().json);
+    // within one source the options still combine as before
+    assert!(matches!(with_opts("--json -c", &["minigrep", "frog"]), Err(ArgsError::Usage(_))));
+
+    // case mode
+    assert!(with_opts("-i", &["minigrep", "-s", "frog"]).unwrap().case_sensitive);
+    let config = with_opts("-S", &["minigrep", "-i", "Frog"]).unwrap();
+    assert!(!config.case_sensitive && !config.smart_case);
+}
+
+#[test]
+fn command_line_turns_off_default_flags() {
+    let opts = "-vnwxEbzro --column --hidden --no-ignore -g *.rs";
+    let env = |key: &str| (key == "MINIGREP_OPTS").then(|| String::from(opts));
+    let config = Config::parse_args_env(args(&["minigrep", "frog"]), env).unwrap();
+    assert!(config.invert_match && config.line_number && config.word_regexp && config.line_regexp && config.regex);
+    assert!(config.byte_offset && config.search_zip && config.recursive && config.only_matching && config.column);
+    assert!(config.hidden && config.no_ignore);
+    assert_eq!(vec!["*.rs"], config.globs);
+
+    let command_line = [
+        "minigrep", "--no-invert-match", "--no-line-number", "--no-word-regexp", "--no-line-regexp",
+        "--no-extended-regexp", "--no-byte-offset", "--no-search-zip", "--no-recursive", "--no-only-matching",
+        "--no-column", "--no-hidden", "--ignore", "--no-glob", "-g", "*.md", "frog",
+    ];
+    let config = Config::parse_args_env(args(&command_line), env).unwrap();
+    assert!(!config.invert_match && !config.line_number && !config.word_regexp && !config.line_regexp && !config.regex);
+    assert!(!config.byte_offset && !config.search_zip && !config.recursive && !config.only_matching && !config.column);
+    assert!(!config.hidden && !config.no_ignore);
+    assert_eq!(vec!["*.md"], config.globs);
+}
+
+#[test]
+fn patterns_and_in_place_cant_be_defaults() {
+    let with_opts = |opts: &'static str, command_line: &[&str]| {
+        Config::parse_args_env(args(command_line), move |key| (key == "MINIGREP_OPTS").then(|| opts.to_string()))
+    };
+    for opts in ["-e TODO", "-eTODO", "--regexp=TODO", "-f patterns.txt", "--replace=x --in-place", "-n -- frog", "-A"] {
+        let result = with_opts(opts, &["minigrep", "frog", "poem.txt"]);
+        assert!(matches!(result, Err(ArgsError::Usage(_))), "{}", opts);
+    }
+
+    // --no-config is only found as an option, not as the value of one
+    let config = with_opts("-n", &["minigrep", "-e", "--no-config", "poem.txt"]).unwrap();
+    assert_eq!(vec!["--no-config"], config.patterns);
+    assert_eq!(vec!["poem.txt"], config.filenames);
+    assert!(config.line_number);
+    assert!(!with_opts("-n", &["minigrep", "--no-config", "frog"]).unwrap().line_number);
+}
@@ -1,4 +1,10 @@
-use minigrep;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+use minigrep::matcher::{Boundary, Matcher};
+use minigrep::walk::{self, WalkOptions};
 
 #[test]
 fn case_sensitive() {
@@ -23,4 +29,451 @@ safe, fast, productive.
 Pick three.
 Trust me.";
     assert_eq!(vec!["Rust:", "Trust me."], minigrep::search_case_insensitive(query, contents));
-}
\ No newline at end of file
+}
+
+#[test]
+fn regex_anchors_and_classes() {
+    let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+    let matcher = Matcher::regex(r"^[A-Z]\w+:$", true).unwrap();
+    assert_eq!(vec!["Rust:"], minigrep::search_with(&matcher, contents));
+}
+
+#[test]
+fn regex_alternation_and_groups() {
+    let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+    let matcher = Matcher::regex(r"(fa|thr)(st|ee)", true).unwrap();
+    assert_eq!(vec!["safe, fast, productive.", "Pick three."], minigrep::search_with(&matcher, contents));
+}
+
+#[test]
+fn regex_case_insensitive() {
+    let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+    let matcher = Matcher::regex(r"^t?rUsT", false).unwrap();
+    assert_eq!(vec!["Rust:", "Trust me."], minigrep::search_with(&matcher, contents));
+}
+
+#[test]
+fn regex_invalid_pattern() {
+    assert!(Matcher::regex(r"(unclosed", true).is_err());
+}
+
+#[test]
+fn walk_recursive_sorted() {
+    let src = format!("{}/src", env!("CARGO_MANIFEST_DIR"));
+    let poem = format!("{}/poem.txt", env!("CARGO_MANIFEST_DIR"));
+    let recursive = WalkOptions { recursive: true, ..Default::default() };
+    let inputs = walk::walk(&[poem.clone(), src.clone()], &recursive, |path, err| panic!("{}: {}", path.display(), err));
+
+    // only files found by walking are skipped if they are binary
+    assert!(!inputs[0].walked && inputs[1..].iter().all(|input| input.walked));
+    let files: Vec<PathBuf> = inputs.into_iter().map(|input| input.path).collect();
+    assert_eq!(PathBuf::from(&poem), files[0]);
+    assert!(files.contains(&PathBuf::from(format!("{}/lib.rs", src))));
+    let mut sorted = files[1..].to_vec();
+    sorted.sort();
+    assert_eq!(sorted, files[1..]);
+}
+
+#[test]
+fn walk_directory_needs_recursive() {
+    let src = format!("{}/src", env!("CARGO_MANIFEST_DIR"));
+    let mut errors = Vec::new();
+    let files = walk::walk(std::slice::from_ref(&src), &WalkOptions::default(), |path, _| errors.push(path.to_path_buf()));
+
+    assert!(files.is_empty());
+    assert_eq!(vec![PathBuf::from(src)], errors);
+}
+
+#[test]
+fn search_reader_matches_search() {
+    let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+    let matcher = Matcher::substring("rUsT", false);
+    let streamed: Vec<(usize, String)> = minigrep::search_reader(&matcher, contents.as_bytes())
+        .map(|m| m.unwrap())
+        .map(|m| (m.line_no, m.line.into_owned()))
+        .collect();
+    assert_eq!(vec![(1, String::from("Rust:")), (4, String::from("Trust me."))], streamed);
+    assert_eq!(minigrep::search_with(&matcher, contents), streamed.iter().map(|(_, line)| line.as_str()).collect::<Vec<_>>());
+}
+
+#[test]
+fn search_reader_larger_than_buffer() {
+    // a file many times bigger than the read buffer, with a match every 1000 lines
+    let buffer_size = 1024;
+    let path = env::temp_dir().join(format!("minigrep_large_{}.txt", std::process::id()));
+    let mut writer = BufWriter::new(File::create(&path).unwrap());
+    for i in 1..=100_000 {
+        if i % 1000 == 0 {
+            writeln!(writer, "line {} has a needle in it\r", i).unwrap();
+        } else {
+            writeln!(writer, "line {} is just hay", i).unwrap();
+        }
+    }
+    writer.flush().unwrap();
+    drop(writer);
+    assert!(fs::metadata(&path).unwrap().len() > 1000 * buffer_size as u64);
+
+    let matcher = Matcher::substring("needle", true);
+    let reader = BufReader::with_capacity(buffer_size, File::open(&path).unwrap());
+    let line_numbers: Vec<usize> = minigrep::search_reader(&matcher, reader)
+        .map(|m| {
+            let m = m.unwrap();
+            assert_eq!(format!("line {} has a needle in it", m.line_no), m.line);
+            m.line_no
+        })
+        .collect();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!((1..=100).map(|i| i * 1000).collect::<Vec<usize>>(), line_numbers);
+}
+
+#[test]
+fn search_reader_literal_skips_lines() {
+    // the literal fast path skips whole lines in the buffer: line numbers and offsets must
+    // still come out right across buffers, for lines longer than the buffer and without "\n"
+    let mut contents = String::new();
+    for i in 1..=2000 {
+        match i % 100 {
+            0 => contents.push_str(&format!("{} needle\r\n", "x".repeat(300))),
+            50 => contents.push_str("needle and another needle\n"),
+            _ => contents.push_str("just hay\n"),
+        }
+    }
+    contents.push_str("last needle");
+    let matcher = Matcher::substring("needle", true);
+    let expected = minigrep::search_matches(&matcher, &contents);
+    assert_eq!(41, expected.len());
+
+    for capacity in [16, 100, 1024] {
+        let reader = BufReader::with_capacity(capacity, contents.as_bytes());
+        let streamed: Vec<_> = minigrep::search_reader(&matcher, reader).map(|m| m.unwrap()).collect();
+        assert_eq!(expected, streamed);
+    }
+}
+
+#[test]
+fn search_reader_invert() {
+    let matcher = Matcher::substring("e", true);
+    let streamed: Vec<(usize, String)> = minigrep::search_reader(&matcher, "Rust:\nPick three.\nfast".as_bytes())
+        .invert(true)
+        .map(|m| m.unwrap())
+        .map(|m| (m.line_no, m.line.into_owned()))
+        .collect();
+    assert_eq!(vec![(1, String::from("Rust:")), (3, String::from("fast"))], streamed);
+}
+
+#[test]
+fn match_positions() {
+    let contents = "Rust:\r\nsafe, fast, productive.\nPick three.\nTrust me, trust me.";
+    let matcher = Matcher::substring("rust", false);
+    let matches = minigrep::search_matches(&matcher, contents);
+
+    assert_eq!(2, matches.len());
+    assert_eq!(1, matches[0].line_no);
+    assert_eq!(0, matches[0].byte_offset);
+    assert_eq!("Rust:", matches[0].line);
+    assert_eq!(vec![0..4], matches[0].ranges);
+
+    assert_eq!(4, matches[1].line_no);
+    assert_eq!(43, matches[1].byte_offset);
+    assert_eq!(vec![1..5, 11..15], matches[1].ranges);
+    assert_eq!(Some(2), matches[1].column());
+
+    // streaming gives the same answers
+    let streamed: Vec<_> = minigrep::search_reader(&matcher, contents.as_bytes()).map(|m| m.unwrap()).collect();
+    assert_eq!(matches, streamed);
+}
+
+#[test]
+fn match_ranges_after_lowercasing() {
+    // 'İ' is 2 bytes but lowercases to 3, the range must still point into the original line
+    let matcher = Matcher::substring("x", false);
+    assert_eq!(vec![2..3, 6..7], matcher.find_ranges("İX İx"));
+
+    let matcher = Matcher::regex(r"\d+", true).unwrap();
+    assert_eq!(vec![5..7, 11..14], matcher.find_ranges("Pick 12 of 345"));
+}
+
+#[test]
+fn search_reader_context() {
+    let contents = "1\n2 x\n3\n4\n5\n6 x\n7\n8\n9\n10\n11 x\n12";
+    let matcher = Matcher::substring("x", true);
+    let lines: Vec<(usize, bool)> = minigrep::search_reader(&matcher, contents.as_bytes())
+        .context(2, 1)
+        .map(|m| m.unwrap())
+        .map(|m| (m.line_no, m.context))
+        .collect();
+    // the context around lines 2 and 6 overlaps and is merged, line 3 only comes out once
+    assert_eq!(
+        vec![(1, true), (2, false), (3, true), (4, true), (5, true), (6, false), (7, true),
+             (9, true), (10, true), (11, false), (12, true)],
+        lines
+    );
+}
+
+#[test]
+fn outcome_exit_codes() {
+    assert_eq!(0, minigrep::Outcome::Matched.exit_code());
+    assert_eq!(1, minigrep::Outcome::NoMatch.exit_code());
+    assert_eq!(2, minigrep::Outcome::Errors { matched: true, errors: 1 }.exit_code());
+}
+
+#[test]
+fn case_insensitive_unicode() {
+    let contents = "\
+Die Straße ist lang.
+STRASSE
+ΟΔΟΣ and οδος
+istanbul
+İstanbul";
+    assert_eq!(vec!["Die Straße ist lang.", "STRASSE"], minigrep::search_case_insensitive("strasse", contents));
+    assert_eq!(vec!["Die Straße ist lang.", "STRASSE"], minigrep::search_case_insensitive("STRAẞE", contents));
+    // final sigma folds like any other sigma
+    assert_eq!(vec!["ΟΔΟΣ and οδος"], minigrep::search_case_insensitive("οδοσ", contents));
+    // dotted capital I folds to "i" followed by a combining dot, not to plain "i"
+    assert_eq!(vec!["İstanbul"], minigrep::search_case_insensitive("i\u{307}stanbul", contents));
+    assert_eq!(vec!["istanbul"], minigrep::search_case_insensitive("ISTANBUL", contents));
+    // other full foldings to more than one char
+    assert_eq!(vec!["ΑΙ"], minigrep::search_case_insensitive("ᾳ", "ΑΙ"));
+    assert_eq!(vec!["J\u{30C}"], minigrep::search_case_insensitive("ǰ", "J\u{30C}"));
+    assert_eq!(vec!["ﬓ"], minigrep::search_case_insensitive("մն", "ﬓ"));
+}
+
+#[test]
+fn case_insensitive_unicode_ranges() {
+    let matcher = Matcher::substring("ss", false);
+    // "ß" is one char (2 bytes) that matches both s
+    assert_eq!(vec![2..4, 8..10], matcher.find_ranges("Maße Mass"));
+
+    // a match can't take only part of what a char folds to: "ß" is "ss", not "s"
+    let matcher = Matcher::substring("S", false);
+    assert!(matcher.find_ranges("Maß").is_empty());
+    assert!(!matcher.is_match("Maß"));
+    let matcher = Matcher::substring("s b", false);
+    assert!(!matcher.is_match("foo ß bar"));
+    let matcher = Matcher::multi(&["s b".into(), "xyz".into()], false).unwrap();
+    assert!(!matcher.is_match("foo ß bar"));
+    assert!(matcher.find_ranges("foo ß bar").is_empty());
+    // a match that takes part of a char doesn't hide the whole-char one right after it
+    let matcher = Matcher::substring("ss", false);
+    assert_eq!(vec![1..3], matcher.find_ranges("sß"));
+    let matcher = Matcher::multi(&["ss".into(), "xyz".into()], false).unwrap();
+    assert_eq!(vec![1..3], matcher.find_ranges("sß"));
+    let matcher = Matcher::bounded(Matcher::substring("ss", false), Boundary::Word);
+    assert_eq!(vec![4..6], matcher.find_ranges("sß ß"));
+
+    // fast path: ASCII line, and a query that can't match ASCII
+    let matcher = Matcher::substring("ΟΔΟΣ", false);
+    assert!(matcher.find_ranges("odos").is_empty());
+    let matcher = Matcher::substring("RuSt", false);
+    assert_eq!(vec![1..5, 6..10], matcher.find_ranges("TRUST rust"));
+}
+
+#[test]
+fn multiple_patterns() {
+    let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+    let patterns = vec![String::from("fast"), String::from("three"), String::from("me")];
+    let matcher = Matcher::multi(&patterns, true).unwrap();
+    assert_eq!(vec!["safe, fast, productive.", "Pick three.", "Trust me."], minigrep::search_with(&matcher, contents));
+    assert_eq!(vec![6..10], matcher.find_ranges("safe, fast, productive."));
+
+    // leftmost-longest, non-overlapping
+    let patterns = vec![String::from("ab"), String::from("abcd"), String::from("cde")];
+    let matcher = Matcher::multi(&patterns, true).unwrap();
+    assert_eq!(vec![0..4], matcher.find_ranges("abcde"));
+
+    let matcher = Matcher::regexes(&[String::from(r"^P\w+"), String::from(r"me\.$")], true).unwrap();
+    assert_eq!(vec!["Pick three.", "Trust me."], minigrep::search_with(&matcher, contents));
+
+    // no patterns match nothing
+    assert!(minigrep::search_with(&Matcher::multi(&[], true).unwrap(), contents).is_empty());
+    assert!(minigrep::search_with(&Matcher::regexes(&[], true).unwrap(), contents).is_empty());
+}
+
+#[test]
+fn multiple_patterns_case_insensitive() {
+    let patterns = vec![String::from("STRASSE"), String::from("οδοσ")];
+    let matcher = Matcher::multi(&patterns, false).unwrap();
+    assert!(matcher.is_match("strasse"));
+    assert_eq!(vec![4..11], matcher.find_ranges("die Straße"));
+    assert_eq!(vec![0..8], matcher.find_ranges("ΟΔΟΣ"));
+    assert!(!matcher.is_match("street"));
+}
+
+#[test]
+fn whole_word() {
+    // same input as case_sensitive, but "duct" inside "productive" isn't a word
+    let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Duct tape.";
+    let matcher = Matcher::bounded(Matcher::substring("duct", true), Boundary::Word);
+    assert!(minigrep::search_with(&matcher, contents).is_empty());
+
+    let matcher = Matcher::bounded(Matcher::substring("duct", false), Boundary::Word);
+    assert_eq!(vec!["Duct tape."], minigrep::search_with(&matcher, contents));
+
+    // a later match can still be a whole word
+    let matcher = Matcher::bounded(Matcher::substring("fast", true), Boundary::Word);
+    assert_eq!(vec![14..18], matcher.find_ranges("breakfast, so fast"));
+    // even one that overlaps a match that isn't
+    let matcher = Matcher::bounded(Matcher::substring("a a", true), Boundary::Word);
+    assert_eq!(vec![3..6], matcher.find_ranges("xa a a"));
+    let matcher = Matcher::bounded(Matcher::multi(&["é".into(), "é é".into()], false).unwrap(), Boundary::Word);
+    assert_eq!(vec![4..9], matcher.find_ranges("xé É é"));
+
+    let matcher = Matcher::bounded(Matcher::regex(r"t\w+", true).unwrap(), Boundary::Word);
+    assert_eq!(vec!["Pick three.", "Duct tape."], minigrep::search_with(&matcher, contents));
+}
+
+#[test]
+fn whole_word_long_line() {
+    // every "a" is a match that isn't a whole word, and the search goes on from the next char:
+    // that has to look for the next match only, not every match in the rest of the line
+    let ascii = "a".repeat(200_000);
+    let folded = "aé".repeat(100_000);
+    let start = std::time::Instant::now();
+    for line in [&ascii, &folded] {
+        for matcher in [
+            Matcher::substring("a", true),
+            Matcher::substring("a", false),
+            Matcher::multi(&["a".into(), "b".into()], true).unwrap(),
+            Matcher::multi(&["a".into(), "b".into()], false).unwrap(),
+            Matcher::regex("a", false).unwrap(),
+        ] {
+            assert!(Matcher::bounded(matcher, Boundary::Word).find_ranges(line).is_empty());
+        }
+    }
+    assert!(start.elapsed() < std::time::Duration::from_secs(10), "{:?}", start.elapsed());
+}
+
+#[test]
+fn whole_line() {
+    let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+pick three.";
+    let matcher = Matcher::bounded(Matcher::substring("Pick three.", true), Boundary::Line);
+    assert_eq!(vec!["Pick three."], minigrep::search_with(&matcher, contents));
+
+    let matcher = Matcher::bounded(Matcher::substring("three.", true), Boundary::Line);
+    assert!(minigrep::search_with(&matcher, contents).is_empty());
+
+    let patterns = vec![String::from("Rust"), String::from("Rust:")];
+    let matcher = Matcher::bounded(Matcher::multi(&patterns, true).unwrap(), Boundary::Line);
+    assert_eq!(vec!["Rust:"], minigrep::search_with(&matcher, contents));
+}
+
+#[test]
+fn literal_fast_path_agrees_with_lines() {
+    let contents = "needle\n\nhay\r\nhay needle hay needle\r\n\nneedle\r\nhay\nlast needle";
+    let matcher = Matcher::substring("needle", true);
+    assert!(matcher.literal().is_some());
+
+    let expected: Vec<&str> = contents.lines().filter(|line| line.contains("needle")).collect();
+    assert_eq!(expected, minigrep::search_with(&matcher, contents));
+
+    let matches = minigrep::search_matches(&matcher, contents);
+    let positions: Vec<(usize, usize)> = matches.iter().map(|m| (m.line_no, m.byte_offset)).collect();
+    assert_eq!(vec![(1, 0), (4, 13), (6, 37), (8, 49)], positions);
+    assert_eq!(vec![4..10, 15..21], matches[1].ranges);
+
+    // the streaming path reports the same positions
+    let streamed: Vec<_> = minigrep::search_reader(&matcher, contents.as_bytes()).map(|m| m.unwrap()).collect();
+    assert_eq!(matches, streamed);
+}
+
+#[test]
+fn literal_fast_path_only_for_plain_queries() {
+    assert!(Matcher::substring("needle", false).literal().is_none());
+    assert!(Matcher::substring("", true).literal().is_none());
+    assert!(Matcher::substring("two\nlines", true).literal().is_none());
+    assert!(Matcher::regex("needle", true).unwrap().literal().is_none());
+}
+
+#[test]
+fn decoder_utf16_and_latin1() {
+    use std::io::Read;
+    use minigrep::encoding::{Decoder, Encoding};
+
+    let text = "frog 🐸\nbog\n";
+    let mut utf16le = vec![0xFF, 0xFE];
+    utf16le.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    // a tiny buffer splits the surrogate pair of the emoji across reads
+    for capacity in [3, 4, 64] {
+        let mut decoded = String::new();
+        let reader = BufReader::with_capacity(capacity, &utf16le[..]);
+        Decoder::new(reader, Encoding::Auto).unwrap().read_to_string(&mut decoded).unwrap();
+        assert_eq!(text, decoded);
+    }
+
+    let utf16be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).chain([0x00]).collect();
+    let mut decoded = String::new();
+    Decoder::new(&utf16be[..], Encoding::Utf16Be).unwrap().read_to_string(&mut decoded).unwrap();
+    assert_eq!(format!("{}\u{FFFD}", text), decoded);
+
+    let mut decoded = String::new();
+    Decoder::new(&b"caf\xE9\n"[..], Encoding::Latin1).unwrap().read_to_string(&mut decoded).unwrap();
+    assert_eq!("café\n", decoded);
+
+    let mut decoded = String::new();
+    Decoder::new(&b"\xEF\xBB\xBFfrog\n"[..], Encoding::Auto).unwrap().read_to_string(&mut decoded).unwrap();
+    assert_eq!("frog\n", decoded);
+}
+
+#[test]
+fn matcher_replace() {
+    let matcher = Matcher::regex(r"(\w+)@(\w+)", true).unwrap();
+    let line = "mail ann@home or bob@work";
+    let ranges = matcher.find_ranges(line);
+    let (replaced, ranges) = matcher.replace(line, &ranges, "$2:${1}$$");
+    assert_eq!("mail home:ann$ or work:bob$", replaced);
+    assert_eq!(vec![5..14, 18..27], ranges);
+
+    // -w only replaces whole words, and plain patterns don't expand $
+    let matcher = Matcher::bounded(Matcher::substring("frog", false), Boundary::Word);
+    let line = "Frogs and a FROG";
+    let (replaced, _) = matcher.replace(line, &matcher.find_ranges(line), "$1");
+    assert_eq!("Frogs and a $1", replaced);
+
+    // with several -e patterns, groups are numbered within the pattern that matched
+    let matcher = Matcher::regexes(&[String::from("(a)"), String::from("(b)"), String::from("x(y)?")], true).unwrap();
+    let line = "xa yb x";
+    let (replaced, _) = matcher.replace(line, &matcher.find_ranges(line), "[$1]");
+    assert_eq!("[][a] y[b] []", replaced);
+}
+
+#[test]
+fn mmap_only_for_big_files() {
+    let path = env::temp_dir().join(format!("minigrep_mmap_{}.txt", std::process::id()));
+    fs::write(&path, "frog\n").unwrap();
+    assert!(minigrep::mmap::map(&File::open(&path).unwrap(), 1).is_none());
+
+    fs::write(&path, "frog\n".repeat(minigrep::mmap::MIN_LEN as usize / 5 + 1)).unwrap();
+    let map = minigrep::mmap::map(&File::open(&path).unwrap(), 1).unwrap();
+    assert!(map.starts_with(b"frog\nfrog\n"));
+    assert!(minigrep::mmap::map(&File::open(&path).unwrap(), minigrep::mmap::MAX_FILES + 1).is_none());
+    fs::remove_file(&path).unwrap();
+}